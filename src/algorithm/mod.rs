@@ -1,19 +1,382 @@
-use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
 // ACO mods
-use crate::graph::Graph;
-use crate::ant::Colony;
+use crate::graph::{self, Graph, GraphError, HeuristicTerm, SelectionRule, Tau};
+use crate::ant::{AcceptancePolicy, Colony, MultiObjective, StartStrategy, UpdateRule};
+// Seeded runs
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 // Progress Bar
-use indicatif::ProgressBar;
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Number of most selected bags reported in the results
+const TOP_BAGS_REPORTED: usize = 5;
+
+/// Smallest evaporation rate a run uses, lower rates are raised to it
+/// so the pheromones cannot grow without bound
+pub const MIN_EVAPORATION_RATE: f64 = 0.001;
+
+/// Evaporation rates outside this band are allowed but warned about
+const RECOMMENDED_EVAPORATION: (f64, f64) = (0.01, 0.9);
+
+/// Adapts the evaporation rate each iteration to keep the colony's
+/// diversity near a target. When the ants' tours become too similar
+/// the rate is raised to forget pheromones faster and re-diversify,
+/// when they are too different the rate is lowered
+///     target_diversity: Diversity to aim for, see Colony::diversity
+///     gain: How strongly the rate reacts to the distance from the target
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveEvaporation {
+    pub target_diversity: f64,
+    pub gain: f64,
+}
+
+impl AdaptiveEvaporation {
+    /// Returns the next evaporation rate given the current rate and
+    /// the colony's diversity, kept within 0 and 1
+    pub fn adjust(&self, evaporation_rate: f64, diversity: f64) -> f64 {
+        (evaporation_rate + self.gain * (self.target_diversity - diversity)).clamp(0.0, 1.0)
+    }
+}
+
+/// Optional settings for a run of the ACO, on top of the
+/// algorithm's parameters. The default leaves every option off
+///     verbose: True if extra infomation should be printed about the algorithm
+///     status_interval: If set, a status line with the global best,
+///         number of evaluations and elapsed time is printed at most
+///         once per interval
+///     start_strategy: Where ants start their tours each iteration
+///     update_rule: How pheromones are updated each iteration
+///     adaptive_evaporation: If set, the evaporation rate is adapted
+///         each iteration, starting from the given evaporation rate
+///     time_limit: If set, the run also stops once this much wall-clock
+///         time has passed, even if fitness_evals has not been met
+///     exclude_warmup: If true, the initial search on random pheromones
+///         only seeds the pheromones, its evaluations, score and history
+///         are discarded and the initial results come from the first
+///         measured iteration instead
+///     warmup_iterations: Number of iterations run before the measured
+///         run, instead of the single initial search. If set above 0 the
+///         warmup is discarded as with exclude_warmup, so it only builds up
+///         pheromone and does not count towards fitness_evals or the history
+///     sort_by_ratio: If true, the bags are stored in ratio order, see
+///         Graph::sort_by_ratio. Reported bag numbers are unaffected
+///     batch_size: If set, each iteration's ants are built in batches of
+///         this size to bound memory, see Colony::run_batched_tours.
+///         Adaptive evaporation then only sees the last batch's diversity
+///     save_pheromone: If set, the pheromone matrix is written to this
+///         file at the end of the run, see Tau::save
+///     load_pheromone: If set, the run starts from the pheromone matrix
+///         in this file instead of random pheromones. The matrix must come
+///         from a run on the same problem with the same sort_by_ratio, a
+///         matrix of another number of bags is an error
+///     min_improvement: Smallest gain over the global best that counts as
+///         an improvement, see Colony::min_improvement
+///     optimum: Known optimal value of the problem, if set the optimality
+///         gap of the final score is added to the results
+///     candidate_list: If set, ants only choose between this many of the
+///         highest ratio bags they can still add, see Graph::candidate_list
+///     disable_learning: If true, the pheromones are initialized but never
+///         evaporated or deposited on, an ablation where selection only
+///         relies on the initial pheromones and the heuristic
+///     disable_heuristic: If true, every bag's heuristic is taken as 1, an
+///         ablation where selection only relies on the pheromones, see
+///         Graph::disable_heuristic. Beta is then ignored
+///     objective: Score the best tours are picked by, see MultiObjective
+///     history_cap: If set, at most this many points of the convergence
+///         history are kept, see Colony::history_cap
+///     selection_rule: If set, replaces tau^alpha * h when weighting the
+///         bags ants choose between, see Graph::selection_rule
+///     acceptance: If set, decides when an iteration's best tour replaces
+///         the incumbent the deposits reinforce, see Colony::acceptance.
+///         Defaults to strict improvement
+///     seed: If set, every random choice of the run comes from a StdRng
+///         seeded with it, so the run can be repeated exactly. Sweeps
+///         derive one seed per run, see derive_seed. If not set a random
+///         seed is drawn, either way the seed is added to the results
+///     plot_convergence: If set, the global best convergence curve is
+///         drawn to this SVG file at the end of the run. Needs the plot
+///         feature, without it a warning is printed instead
+///     value_scale: If set, every reported cost, the scores and averages
+///         and so their differences, is multiplied by it, e.g. 0.001 to
+///         report in thousands. The scale is applied when reporting, the
+///         search itself uses the raw values. Ratios and so h are scale
+///         invariant anyway, as scaling every cost scales every bag's h by
+///         the same factor, which cancels out of the selection probability
+///     strict_budget: If true, the last iteration only sends out as many
+///         ants as the fitness_evals budget has left, so the run stops at
+///         exactly the budget. Otherwise every iteration sends out all
+///         ants, overshooting a budget that is not a multiple of num_of_ants
+///     normalization: If set, the final score divided by the problem's
+///         LP bound or greedy cost is added to the results, so scores of
+///         problems with very different values can be compared
+///     problem_file: If set, the problem is loaded from this file instead
+///         of the default graph::problem_file
+///     local_search: If true, each iteration's best tour is refined by
+///         item swaps, see Colony::swap_search
+///     count_local_search: If true, the local search's probes count
+///         towards fitness_evals, see Colony::count_local_search
+///     tour_metrics: If true, the history records each iteration's
+///         diversity and entropy, see Colony::tour_metrics
+///     heuristic: If set, every bag's heuristic is this blend of
+///         component heuristics instead of ratio^beta, see
+///         Graph::set_heuristic. Beta is then ignored
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    pub verbose: bool,
+    pub status_interval: Option<Duration>,
+    pub start_strategy: StartStrategy,
+    pub update_rule: UpdateRule,
+    pub adaptive_evaporation: Option<AdaptiveEvaporation>,
+    pub time_limit: Option<Duration>,
+    pub exclude_warmup: bool,
+    pub warmup_iterations: usize,
+    pub sort_by_ratio: bool,
+    pub batch_size: Option<usize>,
+    pub save_pheromone: Option<PathBuf>,
+    pub load_pheromone: Option<PathBuf>,
+    pub min_improvement: f64,
+    pub optimum: Option<f64>,
+    pub candidate_list: Option<usize>,
+    pub disable_learning: bool,
+    pub disable_heuristic: bool,
+    pub objective: MultiObjective,
+    pub history_cap: Option<usize>,
+    pub selection_rule: Option<SelectionRule>,
+    pub acceptance: Option<AcceptancePolicy>,
+    pub seed: Option<u64>,
+    pub plot_convergence: Option<PathBuf>,
+    pub value_scale: Option<f64>,
+    pub strict_budget: bool,
+    pub normalization: Option<Normalization>,
+    pub problem_file: Option<PathBuf>,
+    pub local_search: bool,
+    pub count_local_search: bool,
+    pub tour_metrics: bool,
+    pub heuristic: Option<Vec<HeuristicTerm>>,
+}
+
+/// What the normalized score divides the final score by
+///     LpBound: Graph::lp_upper_bound, the normalized score is within [0, 1]
+///     Greedy: The greedy solution's cost, above 1 when ACO beats greedy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Normalization {
+    LpBound,
+    Greedy,
+}
+
+impl RunOptions {
+    /// Returns a cost in the unit it is reported in, see value_scale
+    pub fn report_cost(&self, cost: f64) -> f64 {
+        cost * self.value_scale.unwrap_or(1.0)
+    }
+}
+
+/// A setting or problem that lets a run go ahead, but likely not as intended
+///     ZeroAlpha: Pheromones do not affect selection
+///     EvaporationRate: The rate was clamped or is outside the recommended
+///         band, see check_evaporation_rate
+///     BudgetBelowColony: The budget is spent within the first iteration
+///     AllBagsFit: Every bag fits in the van, taking them all is optimal
+///     NoBagFits: No bag fits in the van on its own, every tour is empty
+///     PlotUnavailable: The convergence plot was asked for without the
+///         plot feature
+///     SkippedBags: Bags of the problem file, by number, dropped as they
+///         had no value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    ZeroAlpha,
+    EvaporationRate(String),
+    BudgetBelowColony { fitness_evals: i64, num_of_ants: i64 },
+    AllBagsFit,
+    NoBagFits,
+    PlotUnavailable(PathBuf),
+    SkippedBags(Vec<i64>),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ZeroAlpha => write!(f, "alpha is 0, pheromones do not affect selection so the colony will not adapt"),
+            Warning::EvaporationRate(message) => write!(f, "{}", message),
+            Warning::BudgetBelowColony { fitness_evals, num_of_ants } => write!(
+                f,
+                "fitness_evals {} is below num_of_ants {}, the budget is spent in the first iteration",
+                fitness_evals, num_of_ants
+            ),
+            Warning::AllBagsFit => write!(f, "every bag fits in the van, so taking all of them is optimal"),
+            Warning::NoBagFits => write!(f, "no bag fits in the van on its own, so every tour is empty"),
+            Warning::PlotUnavailable(path) => write!(f, "{} is not plotted, build with the plot feature", path.display()),
+            Warning::SkippedBags(bags) => write!(f, "bags {:?} have no value and are skipped", bags),
+        }
+    }
+}
+
+/// Warnings raised during a run, in the order they were raised. They
+/// are returned with the results rather than printed, so callers can
+/// inspect them and the CLI prints them once the run is done
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    /// Adds a warning to the collection
+    pub fn push(&mut self, warning: Warning) {
+        self.warnings.push(warning);
+    }
+
+    /// Returns true if the warning was raised
+    pub fn contains(&self, warning: &Warning) -> bool {
+        self.warnings.contains(warning)
+    }
+
+    /// Returns the warnings, in the order they were raised
+    pub fn iter(&self) -> std::slice::Iter<'_, Warning> {
+        self.warnings.iter()
+    }
+
+    /// Returns the number of warnings raised
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// Returns true if no warning was raised
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
+
+/// Results of a run, see run. Scores and averages are reported costs,
+/// see RunOptions::value_scale
+///     seed: Seed of the run, replaying the parameters with it repeats the run
+///     initial_score: Best cost of the initial search, or of the first
+///         measured iteration when the warmup is discarded
+///     initial_avg: Average cost of the same search as initial_score
+///     final_score: Cost of the best tour found across all iterations,
+///         see Colony::best_tour. The tour fields below describe the same tour
+///     final_avg: Average cost of the last iteration
+///     last_improvement: Number of fitness evaluations when the best tour
+///         last improved, see Colony::last_improvement
+///     best_tour: Bag numbers of the best tour, in the order they were visited
+///     best_weight: Weight of the best tour
+///     normalized_score: Final score normalized as RunOptions::normalization
+///         asks, if it does and the bound or cost is positive
+///     optimality_gap: Gap of the final score to RunOptions::optimum, if set
+///         and not 0
+///     unused_capacity: Capacity the best tour leaves unused, see Tour::capacity_slack
+///     slack_admits_bag: True if a bag outside the best tour fits in its unused capacity
+///     improved_over_greedy: True if the final score beats the greedy solution
+///     top_selected_bags: Most selected bags and how often they were
+///         selected, see Colony::top_selected_bags
+///     warnings: Warnings raised during the run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunResults {
+    pub seed: u64,
+    pub initial_score: f64,
+    pub initial_avg: f64,
+    pub final_score: f64,
+    pub final_avg: f64,
+    pub last_improvement: i64,
+    pub best_tour: Vec<i64>,
+    pub best_weight: f64,
+    pub normalized_score: Option<f64>,
+    pub optimality_gap: Option<f64>,
+    pub unused_capacity: f64,
+    pub slack_admits_bag: bool,
+    pub improved_over_greedy: bool,
+    pub top_selected_bags: Vec<(i64, u64)>,
+    pub warnings: Warnings,
+}
+
+/// Receives a run's progress, implemented by the terminal progress bar
+pub trait ProgressSink {
+    /// Sets the number of fitness evaluations done, out of the budget
+    fn set_position(&mut self, position: u64);
+    /// Sets the text shown next to the progress
+    fn set_message(&mut self, message: String);
+}
+
+impl ProgressSink for ProgressBar {
+    fn set_position(&mut self, position: u64) {
+        ProgressBar::set_position(self, position);
+    }
+
+    fn set_message(&mut self, message: String) {
+        ProgressBar::set_message(self, message);
+    }
+}
+
+/// Returns the progress of a run as the evaluations done plus the tours
+/// finished so far in the current iteration, so the progress advances
+/// ant by ant instead of jumping by the colony size. Capped at the budget
+/// evaluations: Fitness evaluations of the completed iterations
+/// finished_ants: Tours finished in the current iteration
+/// fitness_evals: The evaluation budget of the run
+pub fn progress_position(evaluations: i64, finished_ants: usize, fitness_evals: i64) -> u64 {
+    (evaluations + finished_ants as i64).clamp(0, fitness_evals) as u64
+}
+
+/// Reports a completed iteration to the progress sink
+fn report_iteration<P: ProgressSink>(progress: &mut P, colony: &Colony, fitness_evals: i64) {
+    progress.set_message(format!("Iteration {}", colony.history.len()));
+    progress.set_position(progress_position(colony.num_of_fitness_evaluations, 0, fitness_evals));
+}
+
+/// Prints a status line at most once per interval, so long runs
+/// give feedback without flooding the terminal every iteration
+pub struct StatusLine {
+    interval: Duration,
+    start: Instant,
+    last_print: Instant,
+}
+
+impl StatusLine {
+    /// Creates a status line which first prints one interval after start
+    pub fn new(interval: Duration, start: Instant) -> Self {
+        StatusLine { interval, start, last_print: start }
+    }
+
+    /// Writes the status to out if at least one interval has passed
+    /// since the last print
+    /// now: The current time
+    /// global_best: The best tour cost found so far
+    /// evaluations: The current number of fitness evaluations
+    /// Returns true if the status was written
+    pub fn update<W: Write>(&mut self, now: Instant, global_best: f64, evaluations: i64, out: &mut W) -> bool {
+        if now.duration_since(self.last_print) < self.interval {
+            return false;
+        }
+        self.last_print = now;
+        let _ = writeln!(
+            out,
+            "Global Best: {} | Evaluations: {} | Elapsed: {:.1}s",
+            global_best,
+            evaluations,
+            now.duration_since(self.start).as_secs_f64()
+        );
+        true
+    }
+}
 
 /// Runs the ACO algorithm with given parameters
-///     alpha: Weight for edge bias
-///     beta: Weight for heristic bias
-///     evaporation_rate: Direct value applied to all edges, NOT (1 - evaporation_rate)
-///         e.g. edge_phero * evaporation_rate
+///     alpha: Weight for edge bias, 0 gives a pure heuristic search
+///         where pheromones never affect selection and the colony does not learn
+///     beta: Weight for heristic bias, 0 gives a pure pheromone search
+///         where every bag has the same heuristic value
+///     evaporation_rate: Fraction of every edge's pheromone that evaporates each
+///         iteration, so each edge keeps edge_phero * (1 - evaporation_rate).
+///         Near 0 the pheromones barely evaporate and grow without bound,
+///         at 1 they are wiped every iteration. Clamped to
+///         [MIN_EVAPORATION_RATE, 1], see check_evaporation_rate
 ///     num_of_ants: The number of ants to be used
 ///     Fitness_evals: The number of fitness evalutations as a terminal condition
 ///     p_rate: Scalar applied to the pheromones applied to each edge
-///     verbose: True if extra infomation should be printed about the algorithm
+///     options: Optional settings, see RunOptions
+/// Returns the results with the warnings raised, or a GraphError if the
+/// problem could not be loaded
 pub fn run(
         alpha: f64, 
         beta: f64,
@@ -21,54 +384,689 @@ pub fn run(
         num_of_ants:i64, 
         fitness_evals: i64, 
         p_rate: f64, 
-        verbose: bool
-    ) -> HashMap<String, String> {
-    // Stores the results of the ACO
-    let mut results:  HashMap<String, String> = HashMap::new();
+        options: &RunOptions,
+    ) -> Result<RunResults, GraphError> {
+    let verbose = options.verbose;
+    let mut warnings = Warnings::default();
     
+    // Start of the run, for the time limit
+    let start = Instant::now();
+
+    if alpha == 0.0 {
+        warnings.push(Warning::ZeroAlpha);
+    }
+    let (evaporation_rate, warning) = check_evaporation_rate(evaporation_rate);
+    if let Some(warning) = warning {
+        warnings.push(Warning::EvaporationRate(warning));
+    }
+    if fitness_evals < num_of_ants {
+        warnings.push(Warning::BudgetBelowColony { fitness_evals, num_of_ants });
+    }
+
+    // Every run is seeded, so any run can be replayed from its results
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let options = &RunOptions { seed: Some(seed), ..options.clone() };
+
     // Init the colony, 
-    let mut colony: Colony = init_aco(num_of_ants, beta);
+    let mut colony: Colony = init_aco(num_of_ants, beta, options)?;
+    colony.fitness_budget = Some(fitness_evals);
+    if !colony.graph.skipped_bags.is_empty() {
+        warnings.push(Warning::SkippedBags(colony.graph.skipped_bags.clone()));
+    }
+    if let Some(warning) = check_instance(&colony.graph) {
+        warnings.push(warning);
+    }
+    if verbose { println!("{}", colony.graph.summary()); }
     
-    // Progress bar is set to the terminal condition
-    let bar = ProgressBar::new(fitness_evals as u64);
+    // Progress bar is set to the terminal condition, with the iteration and time left
+    let mut bar: Option<ProgressBar> = verbose.then(|| {
+        let bar = ProgressBar::new(fitness_evals as u64);
+        if let Ok(style) = ProgressStyle::with_template("{wide_bar} {pos}/{len} {msg} ETA {eta}") {
+            bar.set_style(style);
+        }
+        bar
+    });
     
-    // Run one search based on random phero values
-    colony.run_tours(alpha);
-    colony.update_edges(evaporation_rate, p_rate);
+    // Without learning the update rule applies no passes, so only the best tours are recorded
+    let update_rule = update_rule(options);
+
+    // Run the warmup, starting with a search based on random phero values
+    warm_up(&mut colony, num_of_ants, alpha, evaporation_rate, p_rate, options)?;
 
     // Add initial search for comparison with final search
-    results.insert("initial_score".to_string(), colony.best_path.1.to_string());
-    results.insert("initial_avg".to_string(), colony.calculate_average_cost().to_string());
+    let mut initial_score = options.report_cost(colony.best_tour().cost());
+    let mut initial_avg = options.report_cost(colony.calculate_average_cost());
     if verbose { write_verbose(&colony)}
+    let discard_warmup = options.exclude_warmup || options.warmup_iterations > 0;
+    if discard_warmup { colony.discard_warmup(); }
+
+    // Throttled status line, if one was asked for
+    let mut status = options.status_interval.map(|interval| StatusLine::new(interval, Instant::now()));
+
+    // Evaporation rate of the current iteration, only changes when adaptive
+    let mut evaporation_rate = evaporation_rate;
 
-    // Run the ACO until the number of evaluations has been met
-    while colony.num_of_fitness_evaluations < fitness_evals {
-        colony.init_ants(num_of_ants);
-        colony.run_tours(alpha);
-        colony.update_edges(evaporation_rate, p_rate);
-        if verbose { bar.set_position(colony.num_of_fitness_evaluations as u64); }
+    // Run the ACO until the number of evaluations or the time limit has been met
+    while colony.num_of_fitness_evaluations < fitness_evals
+        && options.time_limit.is_none_or(|limit| start.elapsed() < limit) {
+        let num_of_ants = iteration_ants(num_of_ants, colony.num_of_fitness_evaluations, fitness_evals, options.strict_budget);
+        match options.batch_size {
+            Some(batch_size) => {
+                let deposits = colony.run_batched_tours(
+                    num_of_ants, batch_size, alpha, options.start_strategy, p_rate, &update_rule
+                );
+                if let Some(adaptive) = options.adaptive_evaporation {
+                    evaporation_rate = adaptive.adjust(evaporation_rate, colony.diversity());
+                }
+                colony.apply_update(evaporation_rate, p_rate, &deposits, &update_rule)?;
+            }
+            None => {
+                colony.init_ants(num_of_ants, options.start_strategy);
+                match bar.as_mut() {
+                    Some(bar) => {
+                        let evaluations = colony.num_of_fitness_evaluations;
+                        colony.run_tours(alpha, Some(&mut |finished_ants| {
+                            bar.set_position(progress_position(evaluations, finished_ants, fitness_evals));
+                        }));
+                    }
+                    None => { colony.run_tours(alpha, None); }
+                }
+                if let Some(adaptive) = options.adaptive_evaporation {
+                    evaporation_rate = adaptive.adjust(evaporation_rate, colony.diversity());
+                }
+                colony.update_edges(evaporation_rate, p_rate, &update_rule)?;
+            }
+        }
+        if let Some(bar) = bar.as_mut() { report_iteration(bar, &colony, fitness_evals); }
+        if let Some(status) = status.as_mut() {
+            status.update(Instant::now(), colony.global_best.cost, colony.num_of_fitness_evaluations, &mut std::io::stdout());
+        }
     }
-    if verbose { write_verbose(&colony)}
+    if verbose { 
+        write_verbose(&colony);
+        println!("Most Selected Bags: {:?}", colony.top_selected_bags(TOP_BAGS_REPORTED));
+    }
+    colony.compact_history();
+
+    // The first measured iteration stands in for the discarded warmup
+    if discard_warmup {
+        if let Some(first) = colony.history.first() {
+            initial_score = options.report_cost(first.iteration_best);
+            initial_avg = options.report_cost(first.average);
+        }
+    }
+
+    // The average comes from the history, as batched runs only keep their last batch of ants
+    let final_avg = colony.history.last().map_or(colony.calculate_average_cost(), |last| last.average);
+    let best_cost = colony.best_tour().cost();
+    let (unused_capacity, slack_admits_bag) = colony.best_tour().capacity_slack(&colony.graph);
+    let mut results = RunResults {
+        seed,
+        initial_score,
+        initial_avg,
+        final_score: options.report_cost(best_cost),
+        final_avg: options.report_cost(final_avg),
+        last_improvement: colony.last_improvement,
+        best_tour: colony.best_tour().bag_numbers(&colony.graph),
+        best_weight: colony.best_tour().weight(),
+        normalized_score: options.normalization.and_then(|normalization| normalized_score(&colony.graph, best_cost, normalization)),
+        optimality_gap: options.optimum.and_then(|optimum| optimality_gap(optimum, best_cost)),
+        unused_capacity,
+        slack_admits_bag,
+        improved_over_greedy: improved_over_greedy(&colony.graph, best_cost),
+        top_selected_bags: colony.top_selected_bags(TOP_BAGS_REPORTED),
+        warnings: Warnings::default(),
+    };
+    if let Some(path) = &options.save_pheromone {
+        colony.graph.tau.save(path)?;
+    }
+    if let Some(path) = &options.plot_convergence {
+        #[cfg(feature = "plot")]
+        crate::plot::write_convergence(path, &colony.history)
+            .map_err(|error| GraphError::Io(format!("{}: {}", path.display(), error)))?;
+        #[cfg(not(feature = "plot"))]
+        warnings.push(Warning::PlotUnavailable(path.clone()));
+    }
+    results.warnings = warnings;
+    Ok(results)
+}
+
+/// Returns a warning if the problem is degenerate, with every bag
+/// fitting in the van or no bag fitting on its own
+fn check_instance(graph: &Graph) -> Option<Warning> {
+    let total_weight: f64 = graph.graph.iter().map(|bag| bag.weight).sum();
+    if graph.within_weight(total_weight, graph.max_weight()) {
+        Some(Warning::AllBagsFit)
+    } else if graph.graph.iter().all(|bag| !graph.within_weight(bag.weight, graph.max_weight())) {
+        Some(Warning::NoBagFits)
+    } else {
+        None
+    }
+}
+
+
+/// Returns the update rule of the run, with no evaporation or
+/// deposit passes when learning is disabled
+fn update_rule(options: &RunOptions) -> UpdateRule {
+    if options.disable_learning {
+        UpdateRule { passes: 0, ..options.update_rule }
+    } else {
+        options.update_rule
+    }
+}
 
-    // Update results with final scores
-    results.insert("final_score".to_string(), colony.best_path.1.to_string());
-    results.insert("final_avg".to_string(), colony.calculate_average_cost().to_string());
-    // Return Results
-    results
+/// Returns the number of ants sent out in the next iteration, all of
+/// them unless the budget is strict and has fewer evaluations left
+/// evaluations: Fitness evaluations done so far
+/// fitness_evals: The evaluation budget of the run
+/// strict_budget: See RunOptions::strict_budget
+pub fn iteration_ants(num_of_ants: i64, evaluations: i64, fitness_evals: i64, strict_budget: bool) -> i64 {
+    if strict_budget {
+        num_of_ants.min(fitness_evals - evaluations).max(0)
+    } else {
+        num_of_ants
+    }
+}
+
+/// Runs the iterations before the measured run, at least the one
+/// initial search on the colony's starting pheromones, or
+/// warmup_iterations if more are asked for. The colony keeps the
+/// iterations' evaluations and history, see Colony::discard_warmup
+fn warm_up(
+        colony: &mut Colony,
+        num_of_ants: i64,
+        alpha: f64,
+        evaporation_rate: f64,
+        p_rate: f64,
+        options: &RunOptions,
+    ) -> Result<(), GraphError> {
+    let update_rule = update_rule(options);
+    for iteration in 0..options.warmup_iterations.max(1) {
+        // The colony's ants are placed when it is created
+        if iteration > 0 { colony.init_ants(num_of_ants, options.start_strategy); }
+        colony.run_tours(alpha, None);
+        colony.update_edges(evaporation_rate, p_rate, &update_rule)?;
+    }
+    Ok(())
 }
 
+/// Clamps an evaporation rate to [MIN_EVAPORATION_RATE, 1], as each
+/// edge keeps (1 - evaporation_rate) of its pheromone per iteration
+/// and a rate outside that would leave the pheromones unbounded or
+/// negative
+/// Returns (the rate to use, a warning if the given rate was clamped
+/// or is outside the recommended band)
+pub fn check_evaporation_rate(evaporation_rate: f64) -> (f64, Option<String>) {
+    let clamped = evaporation_rate.clamp(MIN_EVAPORATION_RATE, 1.0);
+    let (low, high) = RECOMMENDED_EVAPORATION;
+    let warning = if clamped != evaporation_rate {
+        Some(format!("evaporation rate {} is outside [{}, 1], using {}", evaporation_rate, MIN_EVAPORATION_RATE, clamped))
+    } else if clamped < low {
+        Some(format!("evaporation rate {} is below {}, pheromones barely evaporate and can grow very large", clamped, low))
+    } else if clamped > high {
+        Some(format!("evaporation rate {} is above {}, pheromones are almost wiped every iteration", clamped, high))
+    } else {
+        None
+    };
+    (clamped, warning)
+}
+
+/// Derives the seed of one run of a sweep from the sweep's base seed,
+/// so every run is reproducible but no two runs share a seed.
+/// The base seed, the sweep, parameter and run indices are folded in
+/// turn through the SplitMix64 mixer, xor-ing in the next index before
+/// each round. SplitMix64 is a bijection with strong avalanche, so
+/// neighbouring indices give unrelated seeds rather than seeds that
+/// differ in a few bits, which could correlate the runs' random streams
+/// base_seed: Seed of the whole experiment
+/// sweep: Index of the sweep within the experiment, so sweeps whose
+///     settings share an index within their sweep get different seeds
+/// parameter: Index of the parameter setting within the sweep
+/// run: Index of the run within the parameter setting
+pub fn derive_seed(base_seed: u64, sweep: usize, parameter: usize, run: usize) -> u64 {
+    splitmix64(splitmix64(splitmix64(splitmix64(base_seed) ^ sweep as u64) ^ parameter as u64) ^ run as u64)
+}
+
+/// One round of the SplitMix64 generator's output mixing
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Returns the optimality gap of a score, (optimum - score) / optimum,
+/// 0 when the optimum is found
+/// Returns None if the optimum is 0, as the gap is then undefined
+pub fn optimality_gap(optimum: f64, score: f64) -> Option<f64> {
+    if optimum == 0.0 {
+        return None;
+    }
+    Some((optimum - score) / optimum)
+}
+
+/// Returns a score divided by the problem's LP bound or greedy cost,
+/// see Normalization
+/// Returns None if the bound or cost is not positive, e.g. when no bag
+/// fits in the van, as the score cannot be normalized by it
+pub fn normalized_score(graph: &Graph, score: f64, normalization: Normalization) -> Option<f64> {
+    let scale = match normalization {
+        Normalization::LpBound => graph.lp_upper_bound(),
+        Normalization::Greedy => graph.greedy_solution().1,
+    };
+    (scale > 0.0).then(|| score / scale)
+}
+
+/// Returns true if a score beats the greedy solution's cost, false
+/// when ACO only matched the greedy baseline or fell short of it
+pub fn improved_over_greedy(graph: &Graph, score: f64) -> bool {
+    let (_, greedy_cost, _) = graph.greedy_solution();
+    score > greedy_cost
+}
 
 /// Creates the graph and colony for the ACO to
 /// perform with
-fn init_aco(num_of_ants:i64, beta: f64) -> Colony{
-    let graph: Graph = Graph::construct_graph(beta);
-    let mut colony = Colony::new(graph);
-    colony.init_ants(num_of_ants);
-    colony
+fn init_aco(num_of_ants:i64, beta: f64, options: &RunOptions) -> Result<Colony, GraphError> {
+    let path = match &options.problem_file {
+        Some(path) => path.clone(),
+        None => graph::problem_file(&graph::base_dir()),
+    };
+    let graph: Graph = Graph::construct_graph(beta, &path)?;
+    init_colony(graph, num_of_ants, options)
+}
+
+/// Creates the colony over an already constructed graph, with random
+/// pheromones unless a pheromone matrix is loaded
+fn init_colony(mut graph: Graph, num_of_ants: i64, options: &RunOptions) -> Result<Colony, GraphError> {
+    // Ants are placed on random bags, which needs at least one bag
+    if graph.nodes == 0 {
+        return Err(GraphError::EmptyProblem);
+    }
+    if options.sort_by_ratio { graph.sort_by_ratio(); }
+    graph.candidate_list = options.candidate_list;
+    graph.selection_rule = options.selection_rule;
+    graph.disable_heuristic = options.disable_heuristic;
+    if let Some(terms) = &options.heuristic {
+        graph.set_heuristic(terms);
+    }
+    let rng = match options.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let mut colony = match &options.load_pheromone {
+        Some(path) => {
+            let tau = Tau::load(path)?;
+            if tau.size() != graph.nodes {
+                return Err(GraphError::Parse(format!(
+                    "pheromone matrix in {} has {} rows for {} bags", path.display(), tau.size(), graph.nodes
+                )));
+            }
+            graph.tau = tau;
+            Colony::from_parts(graph, Vec::new(), rng)
+        }
+        None => Colony::with_rng(graph, rng),
+    };
+    colony.min_improvement = options.min_improvement;
+    colony.objective = options.objective;
+    colony.history_cap = options.history_cap;
+    colony.acceptance = options.acceptance;
+    colony.local_search = options.local_search;
+    colony.count_local_search = options.count_local_search;
+    colony.tour_metrics = options.tour_metrics;
+    colony.init_ants(num_of_ants, options.start_strategy);
+    Ok(colony)
 }
 
 /// Write the conely and average cost
 fn write_verbose(colony: &Colony) {
     colony.print_colony(false);
-    println!("Average Cost: {}", colony.calculate_average_cost());  
-}
\ No newline at end of file
+    println!("Average Cost: {}", colony.calculate_average_cost());
+    println!("{}", colony.graph.tau.stats());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use crate::graph::{Bag, Heuristic};
+
+    /// Returns bags of weight 10 with the given costs, numbered in order
+    fn bags(costs: &[f64]) -> Vec<Bag> {
+        costs
+            .iter()
+            .enumerate()
+            .map(|(number, cost)| Bag { number: number as i64, weight: 10.0, cost: *cost, ratio: cost / 10.0, h: cost / 10.0 })
+            .collect()
+    }
+
+    /// Returns a colony of 4 ants on the bags after running the given
+    /// number of iterations with the options' start strategy and update rule
+    fn search(capacity: f64, bags: &[Bag], iterations: usize, options: &RunOptions) -> Colony {
+        let mut colony = init_colony(Graph::from_bags(capacity, bags.to_vec()), 4, options).unwrap();
+        for _ in 0..iterations {
+            colony.iterate(4, options.start_strategy, 1.0, 0.1, 1.0, &update_rule(options)).unwrap();
+        }
+        colony
+    }
+
+    /// Tests the status line is only printed once per interval
+    #[test]
+    fn status_line_cadence() {
+        let start = Instant::now();
+        let mut status = StatusLine::new(Duration::from_secs(1), start);
+        let mut out: Vec<u8> = Vec::new();
+
+        assert!(!status.update(start + Duration::from_millis(500), 10.0, 20, &mut out));
+        assert!(status.update(start + Duration::from_millis(1000), 10.0, 40, &mut out));
+        assert!(!status.update(start + Duration::from_millis(1500), 12.0, 60, &mut out));
+        assert!(status.update(start + Duration::from_millis(2100), 15.0, 80, &mut out));
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().collect::<Vec<&str>>(), vec![
+            "Global Best: 10 | Evaluations: 40 | Elapsed: 1.0s",
+            "Global Best: 15 | Evaluations: 80 | Elapsed: 2.1s",
+        ]);
+    }
+
+    /// Tests the adaptive evaporation rate rises as diversity collapses and falls when it recovers
+    #[test]
+    fn adaptive_evaporation() {
+        let adaptive = AdaptiveEvaporation { target_diversity: 0.5, gain: 0.2 };
+        let diversities = [0.1, 0.1, 0.5, 0.9, 0.9];
+        let mut rate = 0.3;
+        let rates: Vec<f64> = diversities
+            .iter()
+            .map(|diversity| {
+                rate = adaptive.adjust(rate, *diversity);
+                rate
+            })
+            .collect();
+        assert!(rates[0] > 0.3 && rates[1] > rates[0]);
+        assert_eq!(rates[2], rates[1]);
+        assert!(rates[3] < rates[2] && rates[4] < rates[3]);
+        assert_eq!(adaptive.adjust(0.99, 0.0), 1.0);
+        assert_eq!(adaptive.adjust(0.01, 1.0), 0.0);
+    }
+
+    /// Tests a saved pheromone matrix loads back unchanged and a loaded colony skips random initialization
+    #[test]
+    fn pheromone_warm_start() {
+        let path = std::env::temp_dir().join(format!("aco_pheromone_{}.csv", std::process::id()));
+        let bags = vec![
+            Bag { number: 0, weight: 10.0, cost: 10.0, ratio: 1.0, h: 1.0 },
+            Bag { number: 1, weight: 10.0, cost: 20.0, ratio: 2.0, h: 2.0 },
+            Bag { number: 2, weight: 10.0, cost: 30.0, ratio: 3.0, h: 3.0 },
+        ];
+        let mut graph = Graph::from_bags(20.0, bags.clone());
+        graph.tau.set_edge(0, 1, 0.123456789);
+        graph.tau.set_edge(1, 2, 1e-9);
+        graph.tau.save(&path).unwrap();
+        let loaded = Tau::load(&path).unwrap();
+        assert_eq!(loaded.size(), 3);
+        assert_eq!(loaded.get_edge(0, 1), 0.123456789);
+        assert_eq!(loaded.get_edge(2, 1), 1e-9);
+
+        // Random initialization never leaves an edge at 0
+        let options = RunOptions { load_pheromone: Some(path.clone()), ..Default::default() };
+        let colony = init_colony(Graph::from_bags(20.0, bags.clone()), 2, &options).unwrap();
+        assert_eq!(colony.graph.tau.get_edge(0, 1), 0.123456789);
+        assert_eq!(colony.graph.tau.get_edge(0, 2), 0.0);
+        assert_eq!(colony.ants.len(), 2);
+        // A matrix of another problem's size is refused
+        assert!(init_colony(Graph::from_bags(20.0, bags[..2].to_vec()), 2, &options).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// Tests a colony cannot be built on a graph without bags
+    #[test]
+    fn empty_graph() {
+        let result = init_colony(Graph::from_bags(20.0, Vec::new()), 4, &RunOptions::default());
+        assert_eq!(result.unwrap_err(), GraphError::EmptyProblem);
+    }
+
+    /// Tests the heuristic option replaces every bag's heuristic with the blend
+    #[test]
+    fn blended_heuristic() {
+        let terms = vec![HeuristicTerm { heuristic: Heuristic::InverseWeight, weight: 2.0, exponent: 1.0 }];
+        let options = RunOptions { heuristic: Some(terms), ..Default::default() };
+        let colony = search(20.0, &bags(&[10.0, 20.0, 30.0]), 0, &options);
+        assert!(colony.graph.graph.iter().all(|bag| bag.h == 2.0 * 0.1));
+    }
+
+    /// Tests evaporation rates at and beyond the extremes are clamped and warned about
+    #[test]
+    fn evaporation_extremes() {
+        let (rate, warning) = check_evaporation_rate(0.0);
+        assert_eq!(rate, MIN_EVAPORATION_RATE);
+        assert!(warning.unwrap().contains("outside"));
+        let (rate, warning) = check_evaporation_rate(1.0);
+        assert_eq!(rate, 1.0);
+        assert!(warning.unwrap().contains("wiped"));
+        assert_eq!(check_evaporation_rate(1.5).0, 1.0);
+        assert!(check_evaporation_rate(0.005).1.unwrap().contains("grow"));
+        assert_eq!(check_evaporation_rate(0.1), (0.1, None));
+
+        // At 1 every edge loses all of its pheromone before the deposit
+        let bags: Vec<Bag> = (0..2).map(|number| Bag { number, weight: 10.0, cost: 10.0, ratio: 1.0, h: 1.0 }).collect();
+        let mut graph = Graph::from_bags(20.0, bags);
+        graph.tau.set_edge(0, 1, 3.0);
+        graph.evaporation_edges(1.0);
+        assert_eq!(graph.tau.get_edge(0, 1), 0.0);
+        graph.tau.set_edge(0, 1, 3.0);
+        graph.evaporation_edges(MIN_EVAPORATION_RATE);
+        assert_eq!(graph.tau.get_edge(0, 1), 3.0 * (1.0 - MIN_EVAPORATION_RATE));
+    }
+
+    /// Tests derived seeds are reproducible and distinct across a grid of sweeps and runs
+    #[test]
+    fn derive_seed() {
+        let grid: Vec<u64> = (0..=3)
+            .flat_map(|sweep| (1..=8).map(move |parameter| (sweep, parameter)))
+            .flat_map(|(sweep, parameter)| (1..=5).map(move |run| super::derive_seed(42, sweep, parameter, run)))
+            .collect();
+        let mut unique = grid.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), grid.len());
+        assert_eq!(super::derive_seed(42, 1, 3, 2), super::derive_seed(42, 1, 3, 2));
+        assert_ne!(super::derive_seed(42, 1, 3, 2), super::derive_seed(43, 1, 3, 2));
+        assert_ne!(super::derive_seed(42, 1, 2, 3), super::derive_seed(42, 1, 3, 2));
+        assert_ne!(super::derive_seed(42, 1, 3, 2), super::derive_seed(42, 2, 3, 2));
+    }
+
+    /// Tests the optimality gap of a score against a known optimum
+    #[test]
+    fn optimality_gap() {
+        assert_eq!(super::optimality_gap(4000.0, 3000.0), Some(0.25));
+        assert_eq!(super::optimality_gap(4000.0, 4000.0), Some(0.0));
+        assert_eq!(super::optimality_gap(0.0, 10.0), None);
+    }
+
+    /// Tests the flag is false when ACO only matches an optimal greedy solution
+    #[test]
+    fn improved_over_greedy() {
+        // Every pair of bags fills the capacity with the same cost, so greedy is optimal
+        let colony = search(20.0, &bags(&[10.0; 4]), 3, &RunOptions::default());
+        assert_eq!(colony.graph.greedy_solution(), (vec![0, 1], 20.0, 20.0));
+        assert_eq!(colony.best_tour().cost, 20.0);
+        assert!(!super::improved_over_greedy(&colony.graph, colony.best_tour().cost));
+        assert!(super::improved_over_greedy(&colony.graph, 21.0));
+    }
+
+    /// Tests the pheromones are unchanged by iterations with learning disabled
+    #[test]
+    fn disable_learning() {
+        let bags = bags(&[10.0, 11.0, 12.0, 13.0]);
+        let options = RunOptions { disable_learning: true, seed: Some(5), ..Default::default() };
+        let mut initial = search(20.0, &bags, 0, &options).graph.tau;
+        let mut colony = search(20.0, &bags, 3, &options);
+        assert_eq!(colony.graph.tau.get_matrix(), initial.get_matrix());
+        assert_eq!(colony.history.len(), 3);
+        assert_eq!(colony.num_of_fitness_evaluations, 12);
+
+        assert_eq!(update_rule(&RunOptions::default()).passes, 1);
+    }
+
+    /// Records every progress update, in place of the terminal bar
+    #[derive(Default)]
+    struct MockBar {
+        positions: Vec<u64>,
+        messages: Vec<String>,
+    }
+
+    impl ProgressSink for MockBar {
+        fn set_position(&mut self, position: u64) {
+            self.positions.push(position);
+        }
+
+        fn set_message(&mut self, message: String) {
+            self.messages.push(message);
+        }
+    }
+
+    /// Tests the progress advances within iterations and reaches the budget exactly when it is met
+    #[test]
+    fn progress_reaches_budget() {
+        let mut colony = search(30.0, &bags(&[10.0; 6]), 2, &RunOptions::default());
+        let fitness_evals = 10;
+        let mut bar = MockBar::default();
+        colony.init_ants(4, StartStrategy::Random);
+        let evaluations = colony.num_of_fitness_evaluations;
+        colony.run_tours(1.0, Some(&mut |finished_ants| {
+            bar.set_position(progress_position(evaluations, finished_ants, fitness_evals));
+        }));
+        colony.update_edges(0.1, 1.0, &UpdateRule::default()).unwrap();
+        report_iteration(&mut bar, &colony, fitness_evals);
+        // Every tour adds 2 bags, all ants are found finished on the third step
+        assert_eq!(bar.positions, vec![8, 8, 10, 10]);
+        assert_eq!(bar.messages, vec!["Iteration 3"]);
+    }
+
+    /// Tests warmup iterations build up pheromone without counting towards the measured run
+    #[test]
+    fn warmup_iterations() {
+        let options = RunOptions { warmup_iterations: 3, ..Default::default() };
+        let mut colony = search(30.0, &bags(&[10.0, 11.0, 12.0, 13.0, 14.0, 15.0]), 0, &options);
+        for i in 0..6 {
+            for j in i+1..6 {
+                colony.graph.tau.set_edge(i, j, 1.0);
+            }
+        }
+        warm_up(&mut colony, 4, 1.0, 0.1, 1.0, &options).unwrap();
+        assert_eq!(colony.num_of_fitness_evaluations, 12);
+        assert_eq!(colony.history.len(), 3);
+
+        colony.discard_warmup();
+        assert_eq!(colony.num_of_fitness_evaluations, 0);
+        assert!(colony.history.is_empty());
+        let edges: Vec<f64> = (0..6).flat_map(|i| (i+1..6).map(move |j| (i, j))).map(|(i, j)| colony.graph.tau.get_edge(i, j)).collect();
+        assert!(edges.iter().any(|edge| *edge != edges[0]));
+
+        let iteration = colony.iterate(4, StartStrategy::Random, 1.0, 0.1, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(iteration.evaluations, 4);
+        assert_eq!(colony.history.len(), 1);
+    }
+
+    /// Tests scaled values only change the reported costs, not the ratios or the search
+    #[test]
+    fn value_scale() {
+        let raw_options = RunOptions { seed: Some(7), ..Default::default() };
+        let scaled_options = RunOptions { value_scale: Some(0.001), ..raw_options.clone() };
+        let raw = run(1.0, 2.0, 0.1, 10, 200, 1.0, &raw_options).unwrap();
+        let scaled = run(1.0, 2.0, 0.1, 10, 200, 1.0, &scaled_options).unwrap();
+
+        assert_eq!(scaled.best_tour, raw.best_tour);
+        assert_eq!(scaled.best_weight, raw.best_weight);
+        assert_eq!(scaled.final_score, raw.final_score * 0.001);
+        assert_eq!(scaled.final_avg, raw.final_avg * 0.001);
+        assert_eq!(scaled.initial_score, raw.initial_score * 0.001);
+    }
+
+    /// Tests a strict budget stops at exactly the budget while the default overshoots it
+    #[test]
+    fn strict_budget() {
+        assert_eq!(iteration_ants(30, 0, 100, true), 30);
+        // The fourth iteration only sends out the 10 ants the budget has left
+        assert_eq!(iteration_ants(30, 90, 100, true), 10);
+        assert_eq!(iteration_ants(30, 90, 100, false), 30);
+        assert_eq!(iteration_ants(30, 100, 100, true), 0);
+        assert_eq!(iteration_ants(30, 110, 100, true), 0);
+    }
+
+    /// Tests warnings are collected and returned rather than printed
+    #[test]
+    fn run_warnings() {
+        let warnings = run(0.0, 2.0, 0.1, 10, 5, 1.0, &RunOptions { seed: Some(3), ..Default::default() }).unwrap().warnings;
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings.contains(&Warning::ZeroAlpha));
+        assert!(warnings.contains(&Warning::BudgetBelowColony { fitness_evals: 5, num_of_ants: 10 }));
+
+        let graph = Graph::from_bags(20.0, vec![Bag { number: 0, weight: 5.0, cost: 5.0, ratio: 1.0, h: 1.0 }]);
+        assert_eq!(check_instance(&graph), Some(Warning::AllBagsFit));
+        let graph = Graph::from_bags(2.0, vec![Bag { number: 0, weight: 5.0, cost: 5.0, ratio: 1.0, h: 1.0 }; 2]);
+        assert_eq!(check_instance(&graph), Some(Warning::NoBagFits));
+
+        // Bags without value are reported by their number in the file
+        let path = std::env::temp_dir().join(format!("aco_skipped_bags_{}.txt", std::process::id()));
+        let bags: String = (1..=5).map(|bag| format!("bag {}:\nweight: 10\nvalue: {}\n", bag, bag % 3 * 10)).collect();
+        fs::write(&path, format!("security van capacity: 20\n{}", bags)).unwrap();
+        let options = RunOptions { problem_file: Some(path.clone()), seed: Some(3), ..Default::default() };
+        let warnings = run(1.0, 2.0, 0.1, 4, 8, 1.0, &options).unwrap().warnings;
+        fs::remove_file(&path).unwrap();
+        assert!(warnings.contains(&Warning::SkippedBags(vec![2])));
+    }
+
+    /// Tests the score normalized by the LP bound is within [0, 1] and by greedy is 1 at the greedy cost
+    #[test]
+    fn normalized_score() {
+        let bags: Vec<Bag> = [(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (12.0, 1.0), (7.0, 9.0), (3.0, 4.0)]
+            .iter()
+            .enumerate()
+            .map(|(number, (weight, cost))| Bag { number: number as i64, weight: *weight, cost: *cost, ratio: cost / weight, h: cost / weight })
+            .collect();
+        let graph = Graph::from_bags(25.0, bags.clone());
+        let (cost, _) = graph.evaluate_tour(&[0, 2]).unwrap();
+        // Bags 2, 0, 5 and 4 fit whole, leaving room for 1 of bag 1's 5 weight
+        assert_eq!(super::normalized_score(&graph, cost, Normalization::LpBound), Some(32.0 / 46.0));
+        let greedy = graph.greedy_solution().1;
+        assert_eq!(super::normalized_score(&graph, greedy, Normalization::Greedy), Some(1.0));
+        // No bag fits, so the greedy cost is 0
+        let graph = Graph::from_bags(2.0, bags);
+        assert_eq!(super::normalized_score(&graph, 0.0, Normalization::Greedy), None);
+    }
+
+    /// Tests the problem is loaded from the given file, and the default file otherwise
+    #[test]
+    fn problem_file() {
+        let path = std::env::temp_dir().join(format!("aco_problem_file_{}.txt", std::process::id()));
+        let bags: String = (1..=5).map(|bag| format!("bag {}:\nweight: 10\nvalue: {}\n", bag, bag * 10)).collect();
+        fs::write(&path, format!("security van capacity: 20\n{}", bags)).unwrap();
+        let options = RunOptions { problem_file: Some(path.clone()), seed: Some(3), ..Default::default() };
+        let colony = init_aco(4, 1.0, &options).unwrap();
+        assert_eq!(colony.graph.nodes, 5);
+        assert_eq!(colony.graph.max_weight(), 20.0);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(init_aco(4, 1.0, &options), Err(GraphError::Io(_))));
+
+        let colony = init_aco(4, 1.0, &RunOptions { seed: Some(3), ..Default::default() }).unwrap();
+        assert_eq!(colony.graph.nodes, 100);
+    }
+
+    /// Tests two runs with the same seed are identical from the pheromones to the best tour
+    #[test]
+    fn seeded_run() {
+        let options = RunOptions { seed: Some(2024), ..Default::default() };
+        let first = run(1.0, 2.0, 0.1, 10, 200, 1.0, &options).unwrap();
+        let second = run(1.0, 2.0, 0.1, 10, 200, 1.0, &options).unwrap();
+        assert!(!first.best_tour.is_empty());
+        assert_eq!(first.best_tour, second.best_tour);
+        assert_eq!(first, second);
+
+        let mut first = init_aco(10, 2.0, &options).unwrap();
+        let mut second = init_aco(10, 2.0, &options).unwrap();
+        assert_eq!(first.graph.tau.get_matrix(), second.graph.tau.get_matrix());
+        let starts = |colony: &Colony| colony.ants.iter().map(|ant| ant.current_bag).collect::<Vec<usize>>();
+        assert_eq!(starts(&first), starts(&second));
+    }
+}
@@ -1,54 +1,421 @@
 use core::fmt;
 use std::cmp::Ordering;
-use rand::Rng;
-use crate::graph::Graph;
+use std::collections::HashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::graph::{self, DepositRule, Graph, GraphError, Tau, MAX_PHEROMONE};
+
+/// Where ants start their tours
+///     Random: Each ant starts at a uniformly random bag
+///     TopRatio: Ants start at the bags with the highest cost/weight
+///         ratio, one ant per bag in ratio order, wrapping around if
+///         there are more ants than bags
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StartStrategy {
+    #[default]
+    Random,
+    TopRatio,
+}
+
+/// Order evaporation and deposit are applied in within one pheromone update
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UpdateOrder {
+    #[default]
+    EvaporateThenDeposit,
+    DepositThenEvaporate,
+}
+
+/// What happens when a pheromone value overflows to inf or NaN,
+/// which high p_rates over many iterations can cause
+///     Clamp: The edge is set to graph::MAX_PHEROMONE and the run continues
+///     Error: The update returns GraphError::PheromoneOverflow
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum OverflowGuard {
+    #[default]
+    Clamp,
+    Error,
+}
+
+/// Which tours deposit pheromone in an update
+///     AllAnts: Every ant of the iteration deposits along its tour
+///     IterationBest: Only the best tour of the iteration deposits
+///     GlobalBest: Only the colony's incumbent deposits, the best tour
+///         found so far unless an acceptance policy picked another
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DepositSource {
+    #[default]
+    AllAnts,
+    IterationBest,
+    GlobalBest,
+}
+
+/// How much each edge evaporates in an update
+///     Uniform: Every edge keeps (1 - evaporation_rate) of its pheromone
+///     EdgeAge: Edges evaporate faster the longer ago they last got a
+///         deposit, at evaporation_rate * (1 + gain * age) capped at 1,
+///         where age is the number of updates since, see Colony::edge_age
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum EvaporationMode {
+    #[default]
+    Uniform,
+    EdgeAge { gain: f64 },
+}
+
+/// Controls how Colony::update_edges updates the pheromones
+///     reinforce_global_best: If true, the colony's incumbent gets one extra
+///         deposit along its edges after the normal per-ant deposit
+///     order: Whether evaporation happens before or after the deposit
+///     passes: Number of times evaporation and deposit are applied per update
+///     deposit: How much pheromone each tour deposits, see DepositRule
+///     overflow: How pheromones that overflow are handled, see OverflowGuard
+///     source: Which tours deposit, see DepositSource
+///     evaporation: How much each edge evaporates, see EvaporationMode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpdateRule {
+    pub reinforce_global_best: bool,
+    pub order: UpdateOrder,
+    pub passes: usize,
+    pub deposit: DepositRule,
+    pub overflow: OverflowGuard,
+    pub source: DepositSource,
+    pub evaporation: EvaporationMode,
+}
+
+impl Default for UpdateRule {
+    /// Evaporate every edge uniformly then deposit value per weight from
+    /// every ant, once, without reinforcement, clamping overflowed pheromones
+    fn default() -> Self {
+        UpdateRule {
+            reinforce_global_best: false,
+            order: UpdateOrder::EvaporateThenDeposit,
+            passes: 1,
+            deposit: DepositRule::ValuePerWeight,
+            overflow: OverflowGuard::Clamp,
+            source: DepositSource::AllAnts,
+            evaporation: EvaporationMode::Uniform,
+        }
+    }
+}
+
+/// The metrics of one iteration, recorded in the colony's convergence
+/// history every time the colony's best tour is set and returned by
+/// Colony::iterate, so loggers and dashboards read every metric from
+/// one type
+///     iteration: Number of the iteration, starting at 1
+///     evaluations: Number of fitness evaluations so far
+///     iteration_best: Cost of the best tour of the iteration
+///     global_best: Cost of the best tour found so far
+///     average: Average tour cost of the iteration
+///     diversity: Colony::diversity of the iteration's tours, if the
+///         colony's tour_metrics are enabled
+///     entropy: Colony::entropy of the iteration's tours, likewise
+/// Batched iterations only keep their last batch of ants, so their
+/// diversity and entropy are of the last batch
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationResult {
+    pub iteration: usize,
+    pub evaluations: i64,
+    pub iteration_best: f64,
+    pub global_best: f64,
+    pub average: f64,
+    pub diversity: Option<f64>,
+    pub entropy: Option<f64>,
+}
+
+/// Returns the slope of the global best curve over the trailing window
+/// of iterations, the improvement per iteration between the latest
+/// point of the history and the earliest one within the window. A slope
+/// near 0 means the run has converged, so it can serve as a stopping
+/// criterion. Iteration numbers are used, so a compacted history works
+/// history: Convergence history, see Colony::history
+/// window: Number of trailing iterations the slope is measured over
+/// Returns None if the window holds fewer than two points
+pub fn improvement_rate(history: &[IterationResult], window: usize) -> Option<f64> {
+    let last = history.last()?;
+    let first = history
+        .iter()
+        .find(|point| last.iteration - point.iteration <= window)
+        .filter(|first| first.iteration < last.iteration)?;
+    Some((last.global_best - first.global_best) / (last.iteration - first.iteration) as f64)
+}
+
+/// Pheromone deposited on each edge by an update, keyed by the edge's
+/// bags as (lower index, higher index). Only edges that get a deposit
+/// are stored, so the deposits of an iteration stay as small as its tours
+pub type Deposits = HashMap<(usize, usize), f64>;
+
+/// Adds a deposit to an edge, in either direction
+fn add_deposit(deposits: &mut Deposits, (bag_i, bag_j): (usize, usize), value: f64) {
+    *deposits.entry((bag_i.min(bag_j), bag_i.max(bag_j))).or_insert(0.0) += value;
+}
+
+/// Returns the consecutive pairs of bags of a tour, see Ant::edges
+fn edges(bags: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    bags.windows(2).map(|edge| (edge[0], edge[1]))
+}
+
+/// Scalarized score of a tour, used to pick the best tours. Each
+/// objective is multiplied by its weight and summed, so a negative
+/// weight penalizes an objective
+///     cost_weight: Weight of the tour's total cost
+///     count_weight: Weight of the number of bags in the tour
+///     weight_weight: Weight of the tour's total weight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultiObjective {
+    pub cost_weight: f64,
+    pub count_weight: f64,
+    pub weight_weight: f64,
+}
+
+impl Default for MultiObjective {
+    /// Cost alone, the original objective
+    fn default() -> Self {
+        MultiObjective {
+            cost_weight: 1.0,
+            count_weight: 0.0,
+            weight_weight: 0.0,
+        }
+    }
+}
+
+impl MultiObjective {
+    /// Returns the score of a tour with the given cost, number of bags and weight
+    pub fn score(&self, cost: f64, bags: usize, weight: f64) -> f64 {
+        self.cost_weight * cost + self.count_weight * bags as f64 + self.weight_weight * weight
+    }
+
+    /// Returns the score of a tour
+    pub fn score_tour(&self, tour: &Tour) -> f64 {
+        self.score(tour.cost, tour.len(), tour.weight)
+    }
+}
+
+/// A tour through the graph
+///     bags: Bags in the order they were visited, as graph indices
+///     cost: Total cost of the tour's bags
+///     weight: Total weight of the tour's bags
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tour {
+    pub bags: Vec<usize>,
+    pub cost: f64,
+    pub weight: f64,
+}
+
+impl Tour {
+    /// Returns the number of bags in the tour
+    pub fn len(&self) -> usize {
+        self.bags.len()
+    }
+
+    /// Returns true if the tour has no bags
+    pub fn is_empty(&self) -> bool {
+        self.bags.is_empty()
+    }
+
+    /// Returns the total cost of the tour's bags
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    /// Returns the total weight of the tour's bags
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Returns the edges the tour traversed, see Ant::edges
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        edges(&self.bags)
+    }
+
+    /// Returns the tour's bags as the bag numbers of the problem file
+    pub fn bag_numbers(&self, graph: &Graph) -> Vec<i64> {
+        self.bags.iter().map(|bag| graph.number_at(*bag)).collect()
+    }
+
+    /// Returns true if the tour's weight is within the graph's capacity.
+    /// With several vans this is their total capacity, as a tour does
+    /// not record which van each bag went in
+    pub fn is_feasible(&self, graph: &Graph) -> bool {
+        graph.within_weight(self.weight, graph.total_capacity())
+    }
+
+    /// Returns the capacity the tour leaves unused and whether any bag
+    /// outside the tour would still fit in it. A bag that fits means the
+    /// tour could simply be extended, so it cannot be optimal. With
+    /// several vans the unused capacity is of all of them, see is_feasible
+    pub fn capacity_slack(&self, graph: &Graph) -> (f64, bool) {
+        let unused_capacity = graph.total_capacity() - self.weight;
+        let admits_bag = (0..graph.nodes)
+            .filter(|bag| !self.bags.contains(bag))
+            .any(|bag| graph.within_weight(graph.graph[bag].weight, unused_capacity));
+        (unused_capacity, admits_bag)
+    }
+}
+
+impl From<&Ant> for Tour {
+    fn from(ant: &Ant) -> Self {
+        Tour {
+            bags: ant.tour.clone(),
+            cost: ant.current_cost,
+            weight: ant.current_weight,
+        }
+    }
+}
+
+/// Decides whether the iteration's best tour replaces the incumbent the
+/// deposits reinforce, given the objective scores of the incumbent and
+/// the candidate and the fraction of the fitness budget used, 0 if the
+/// colony has no budget. Lets a worse tour become the incumbent, e.g.
+/// early in the run, without losing the best tour found so far.
+/// A plain fn rather than a boxed closure, so the colony and the run
+/// options stay Clone and Debug, any schedule is derived from the
+/// fraction of the budget instead of captured state
+pub type AcceptancePolicy = fn(incumbent_cost: f64, candidate_cost: f64, iteration_fraction: f64) -> bool;
 
 /// Stores graph, ants and meta information for 
 /// ACO.
 ///     Graph: Graph struct type contains all bag references and pheromone information
 ///     Ants: Collection fo Ant struct types
-///     Best Path: Contains data in the order off:
-///         (Tour as Vec<Bag references as usize>, cost, weight)
+///     Best Path: Best tour of the latest iteration
+///     Global Best: Best tour found across all iterations
+///     incumbent: Tour the global best deposits reinforce, the global best
+///         unless the acceptance policy accepted another tour
 ///     num_of_fitness_evaluations: Current number of fitness evalutations in the ACO
-pub struct Colony {
+///     bag_selections: Number of times each bag was added to an ant's tour, indexed as the graph
+///     history: Convergence history, one point per iteration
+///     history_cap: If set, the history is down-sampled to keep memory
+///         bounded on long runs. Only every history_stride-th iteration
+///         is kept, plus the latest, and the stride doubles whenever more
+///         than twice the cap is stored, see compact_history
+///     history_stride: Iterations between the kept history points
+///     min_improvement: Smallest gain over the global best that counts as
+///         an improvement, smaller gains still update the global best but
+///         do not reset the stagnation tracking
+///     stagnation: Number of iterations since the global best last improved
+///     last_improvement: Number of fitness evaluations when the global best last improved
+///     objective: Score the best tours are picked by, see MultiObjective
+///     edge_age: Number of updates since each edge last got a deposit,
+///         kept alongside the pheromones but only counted when the update
+///         rule evaporates by EvaporationMode::EdgeAge
+///     branching_steps: Number of ant steps of the current iteration that
+///         had at least one bag to choose from
+///     branching_choices: Total number of bags to choose from over those
+///         steps, see average_branching_factor
+///     acceptance: If set, decides whether an iteration's best tour
+///         replaces the incumbent instead of strict improvement, see
+///         AcceptancePolicy. The global best is always the best tour found
+///     fitness_budget: Fitness evaluations the run is given, the
+///         acceptance policy is passed the fraction of it used so far
+///     local_search: If true, each iteration's best tour is refined by
+///         item swaps before it is recorded, see swap_search
+///     count_local_search: If true, every swap tried by the local search
+///         counts as a fitness evaluation, so the budget reflects the
+///         local search's work. Otherwise only the ants' tours count
+///     tour_metrics: If true, the diversity and entropy of each
+///         iteration's tours are added to the history. Both compare every
+///         ant's tour, so they are left out unless asked for
+#[derive(Debug, Clone)]
+pub struct Colony<R: Rng = StdRng> {
     pub graph: Graph,
     pub ants: Vec<Ant>,
-    pub best_path: (Vec<usize>, f64, f64),
+    pub best_path: Tour,
+    pub global_best: Tour,
+    pub incumbent: Tour,
     pub num_of_fitness_evaluations: i64,
+    pub bag_selections: Vec<u64>,
+    pub history: Vec<IterationResult>,
+    pub history_cap: Option<usize>,
+    history_stride: usize,
+    pub min_improvement: f64,
+    pub stagnation: usize,
+    pub last_improvement: i64,
+    pub objective: MultiObjective,
+    pub edge_age: Tau,
+    branching_steps: u64,
+    branching_choices: u64,
+    pub acceptance: Option<AcceptancePolicy>,
+    pub fitness_budget: Option<i64>,
+    pub local_search: bool,
+    pub count_local_search: bool,
+    pub tour_metrics: bool,
+    pub rng: R,
 }
 
-impl fmt::Display for Colony {
+impl<R: Rng> fmt::Display for Colony<R> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "Graph Size: {}\nNumber of Ants: {}\nBest Path Cost\\weight: {}\\{}\nBest Path: {:?}",
             self.graph.nodes,
             self.ants.len(),
-            self.best_path.1,
-            self.best_path.2,
-            self.best_path.0.iter()
-                .map(|bag| format!("{}", self.graph.graph[*bag].number))
+            self.best_path.cost,
+            self.best_path.weight,
+            self.best_path.bag_numbers(&self.graph).iter()
+                .map(|bag| bag.to_string())
                 .collect::<Vec<String>>().join(" -> ")
         )
     }
 }
 
-impl Colony {
+impl Colony<StdRng> {
     /// Returns a new coloney with the given graph,
     /// best path is set to an empty vector, with 
     /// cost and weight as 0.0
-    pub fn new(mut graph: Graph) -> Self {
+    /// Random choices are made by a StdRng seeded from the OS
+    pub fn new(graph: Graph) -> Self {
+        Colony::with_rng(graph, StdRng::from_entropy())
+    }
+    
+    /// Returns a colony with the given graph and ants, bypassing
+    /// init_ants. Tau is left as it is in the given graph, so
+    /// hand-crafted pheromone values are kept
+    pub fn from_graph_and_ants(graph: Graph, ants: Vec<Ant>) -> Self {
+        Colony::from_parts(graph, ants, StdRng::from_entropy())
+    }
+}
+
+impl<R: Rng> Colony<R> {
+    /// Returns a new colony with the given graph, as Colony::new,
+    /// making all random choices with the given generator
+    /// rng: Any random number generator, e.g. a seeded StdRng or SmallRng
+    pub fn with_rng(mut graph: Graph, mut rng: R) -> Self {
         // Adds a uniform distribution of pheromones values to the 
         // Tau structure
-        graph.initialize_tau();
-        Colony { 
-            graph: graph,
-            ants: Vec::new(),
-            best_path: (Vec::new(), 0.0, 0.0), 
+        graph.initialize_tau(&mut rng);
+        Colony::from_parts(graph, Vec::new(), rng)
+    }
+
+    /// Returns a colony with the given graph, ants and generator,
+    /// as Colony::from_graph_and_ants
+    pub fn from_parts(graph: Graph, ants: Vec<Ant>, rng: R) -> Self {
+        let nodes = graph.nodes;
+        Colony {
+            graph,
+            ants,
+            best_path: Tour::default(),
+            global_best: Tour::default(),
+            incumbent: Tour::default(),
             num_of_fitness_evaluations: 0,
+            bag_selections: vec![0; nodes],
+            history: Vec::new(),
+            history_cap: None,
+            history_stride: 1,
+            min_improvement: 0.0,
+            stagnation: 0,
+            last_improvement: 0,
+            objective: MultiObjective::default(),
+            edge_age: Tau::new(nodes),
+            branching_steps: 0,
+            branching_choices: 0,
+            acceptance: None,
+            fitness_budget: None,
+            local_search: false,
+            count_local_search: false,
+            tour_metrics: false,
+            rng,
         }
     }
-    
+
     /// Prints the colony's data,
     /// if verbose is true then the best path is included
     pub fn print_colony(&self, verbose: bool) {
@@ -56,77 +423,292 @@ impl Colony {
             println!("Graph Size: {}\nNumber of Ants: {}\nBest Path Cost\\weight: {}\\{}\nBest Path: {:?}",
                 self.graph.nodes,
                 self.ants.len(),
-                self.best_path.1,
-                self.best_path.2,
-                self.best_path.0.iter()
-                    .map(|bag| format!("{}", self.graph.graph[*bag].number))
+                self.best_path.cost,
+                self.best_path.weight,
+                self.best_path.bag_numbers(&self.graph).iter()
+                    .map(|bag| bag.to_string())
                     .collect::<Vec<String>>().join(" -> ")
             );
         } else {
             println!("Graph Size: {}\nNumber of Ants: {}\nBest Path Cost-weight: {}\\{}",
                 self.graph.nodes,
                 self.ants.len(),
-                self.best_path.1,
-                self.best_path.2.round(),
+                self.best_path.cost,
+                self.best_path.weight.round(),
                 );
         }
     }
 
-    /// Fill the colony with new ants at random bags
-    pub fn init_ants(&mut self, num_of_ants: i64) {
-        self.ants = Vec::new();
-        let mut rng = rand::thread_rng();
-        for _ in 0..num_of_ants {
-            let bag = rng.gen_range(0..self.graph.nodes);
-            self.ants.push(Ant::birth(bag, &self.graph));
+    /// Adds an already built ant to the colony, so an update can be run
+    /// on known tours instead of ones from init_ants
+    pub fn push_ant(&mut self, ant: Ant) {
+        self.ants.push(ant);
+    }
+
+    /// Fill the colony with new ants, placed according to
+    /// the start strategy. A graph with no bags has nowhere to
+    /// place an ant, so the colony is left without ants
+    pub fn init_ants(&mut self, num_of_ants: i64, start_strategy: StartStrategy) {
+        self.reset_branching();
+        self.ants = self.spawn_ants(num_of_ants as usize, 0, start_strategy);
+    }
+
+    /// Creates count new ants, placed according to the start strategy.
+    /// first_ant is the position of the first new ant within the
+    /// iteration's ants, so batches of ants continue where the
+    /// previous batch stopped
+    fn spawn_ants(&mut self, count: usize, first_ant: usize, start_strategy: StartStrategy) -> Vec<Ant> {
+        if self.graph.nodes == 0 {
+            return Vec::new();
         }
+        let ranked_bags: Vec<usize> = match start_strategy {
+            StartStrategy::Random => Vec::new(),
+            StartStrategy::TopRatio => self.graph.indices_by_ratio(),
+        };
+        (first_ant..first_ant + count)
+            .map(|ant| {
+                let bag = match start_strategy {
+                    StartStrategy::Random => self.rng.gen_range(0..self.graph.nodes),
+                    StartStrategy::TopRatio => ranked_bags[ant % ranked_bags.len()],
+                };
+                Ant::birth(bag, &self.graph)
+            })
+            .collect()
+    }
+
+    /// Constructs the tours of num_of_ants ants in batches of batch_size,
+    /// so only one batch of tours is held in memory at a time. Each batch's
+    /// deposits are added to the sparse Deposits before the batch is dropped,
+    /// and the iteration is recorded as a whole once all batches are done.
+    /// Passing the returned deposits to apply_update gives the same
+    /// pheromones as building every ant at once and calling update_edges.
+    /// Only the last batch is left in the colony's ants
+    /// rule: The update rule, its deposit and source decide the deposits
+    pub fn run_batched_tours(
+        &mut self,
+        num_of_ants: i64,
+        batch_size: usize,
+        alpha: f64,
+        start_strategy: StartStrategy,
+        p_rate: f64,
+        rule: &UpdateRule,
+    ) -> Deposits {
+        let num_of_ants = num_of_ants as usize;
+        self.reset_branching();
+        let mut deposits = Deposits::new();
+        let mut best: Option<Tour> = None;
+        let mut total_cost: f64 = 0.0;
+        let mut built: usize = 0;
+        while built < num_of_ants {
+            let count = batch_size.max(1).min(num_of_ants - built);
+            self.ants = self.spawn_ants(count, built, start_strategy);
+            let batch_best = self.run_tours(alpha, None);
+            if rule.source == DepositSource::AllAnts {
+                self.deposit_tours(&mut deposits, p_rate, rule);
+            }
+            total_cost += self.calculate_total_colony_cost();
+            if best.as_ref().is_none_or(|best| self.objective.score_tour(&batch_best) > self.objective.score_tour(best)) {
+                best = Some(batch_best);
+            }
+            built += count;
+        }
+        if let Some(best) = best {
+            self.record_iteration(best, num_of_ants as i64, total_cost / num_of_ants as f64);
+        }
+        // The best tours are only known once every batch is done
+        if rule.source != DepositSource::AllAnts {
+            self.deposit_tours(&mut deposits, p_rate, rule);
+        }
+        deposits
     }
 
     /// Constructs all the ants tours. A tour is complete
     /// if no other bag can be added due to the weight 
     /// constraint
-    /// Returns true when finished
-    pub fn run_tours(&mut self, alpha: f64) -> bool {
-        while !self.are_all_tours_finished() {
-            self.time_step(alpha);
+    /// alpha: Scalar value applied to pheromone levels
+    /// on_step: If set, called with the number of ants whose tours
+    ///     are finished after each time step
+    /// Returns the best of the constructed tours, an empty
+    /// tour if the colony has no ants
+    pub fn run_tours(&mut self, alpha: f64, mut on_step: Option<&mut dyn FnMut(usize)>) -> Tour {
+        loop {
+            let finished_ants = self.time_step(alpha);
+            if let Some(on_step) = on_step.as_mut() {
+                on_step(finished_ants);
+            }
+            if finished_ants == self.ants.len() {
+                break;
+            }
         }
-        return true
+        self.top_ant().map(Tour::from).unwrap_or_default()
     }
 
     /// Adds one bag to each ants tour if there is a
     /// bag within the weight constraint
-    pub fn time_step(&mut self, alpha: f64) {
+    /// Returns the number of ants no bag could be added to, whose
+    /// tours are finished
+    pub fn time_step(&mut self, alpha: f64) -> usize {
+        let mut finished_ants = 0;
         for ant in self.ants.iter_mut() {
-            ant.update_ant(&self.graph, alpha);
+            let choices = ant.update_ant(&self.graph, alpha, &mut self.bag_selections, &mut self.rng);
+            if choices > 0 {
+                self.branching_steps += 1;
+                self.branching_choices += choices as u64;
+            } else {
+                finished_ants += 1;
+            }
         }
+        finished_ants
+    }
+
+    /// Returns the mean number of bags an ant had to choose from per
+    /// step of the current iteration, counting only the steps that added
+    /// a bag. A low branching factor means the problem is tightly
+    /// constrained. Returns 0 if no ant has taken a step yet
+    pub fn average_branching_factor(&self) -> f64 {
+        if self.branching_steps == 0 {
+            return 0.0;
+        }
+        self.branching_choices as f64 / self.branching_steps as f64
+    }
+
+    /// Starts counting the branching factor of a new iteration
+    fn reset_branching(&mut self) {
+        self.branching_steps = 0;
+        self.branching_choices = 0;
     }
 
     /// Updates all edges through pheromone evaporation and pheromone updating
     /// evaporation_rate: Evaporation scalar
     /// p_rate: Pheromone scalar
-    pub fn update_edges(&mut self, evaporation_rate: f64, p_rate: f64) {
+    /// rule: How the update is applied, see UpdateRule
+    /// Returns a GraphError if a pheromone overflows and the rule's guard is Error
+    pub fn update_edges(&mut self, evaporation_rate: f64, p_rate: f64, rule: &UpdateRule) -> Result<(), GraphError> {
         // Panics if edges are updates before ants have finished their tours,
         // this should never happen unless the algorithm is not running
         // as intended
-        match self.set_best_tour() {
-            Some(_) => {
-                panic!("Ealier call to update, ants had not finished their tours!!!");
+        if self.set_best_tour().is_some() {
+            panic!("Ealier call to update, ants had not finished their tours!!!");
+        }
+
+        let mut deposits = Deposits::new();
+        self.deposit_tours(&mut deposits, p_rate, rule);
+        self.apply_update(evaporation_rate, p_rate, &deposits, rule)
+    }
+
+    /// Applies evaporation and the given deposits to the edges,
+    /// according to the update rule
+    /// evaporation_rate: Evaporation scalar
+    /// p_rate: Pheromone scalar, used for the global best reinforcement
+    /// deposits: Pheromones deposited by the ants, see deposit_tours
+    /// rule: How the update is applied, see UpdateRule
+    /// Returns a GraphError if a pheromone overflows and the rule's guard is Error
+    pub fn apply_update(&mut self, evaporation_rate: f64, p_rate: f64, deposits: &Deposits, rule: &UpdateRule) -> Result<(), GraphError> {
+        if let EvaporationMode::EdgeAge { .. } = rule.evaporation {
+            self.age_edges(deposits, rule);
+        }
+        for _ in 0..rule.passes {
+            match rule.order {
+                UpdateOrder::EvaporateThenDeposit => {
+                    self.evaporate(evaporation_rate, rule.evaporation);
+                    self.deposit(deposits, p_rate, rule);
+                }
+                UpdateOrder::DepositThenEvaporate => {
+                    self.deposit(deposits, p_rate, rule);
+                    self.evaporate(evaporation_rate, rule.evaporation);
+                }
+            }
+        }
+        self.guard_overflow(rule.overflow)
+    }
+
+    /// Evaporates the edges according to the evaporation mode
+    fn evaporate(&mut self, evaporation_rate: f64, mode: EvaporationMode) {
+        match mode {
+            EvaporationMode::Uniform => self.graph.evaporation_edges(evaporation_rate),
+            EvaporationMode::EdgeAge { gain } => self.graph.evaporation_edges_by_age(evaporation_rate, gain, &self.edge_age),
+        }
+    }
+
+    /// Counts one more update on every edge's age, except the edges
+    /// getting a deposit in this update which start again from 0
+    /// deposits: Pheromones deposited by the ants, see deposit_tours
+    /// rule: The update rule, for the global best reinforcement
+    fn age_edges(&mut self, deposits: &Deposits, rule: &UpdateRule) {
+        let nodes = self.graph.nodes;
+        for i in 0..nodes {
+            for j in i+1..nodes {
+                let deposited = deposits.get(&(i, j)).is_some_and(|value| *value > 0.0);
+                let age = if deposited { 0.0 } else { self.edge_age.get_edge(i, j) + 1.0 };
+                self.edge_age.set_edge(i, j, age);
+            }
+        }
+        if rule.reinforce_global_best {
+            for (bag_i, bag_j) in self.incumbent.edges() {
+                self.edge_age.set_edge(bag_i, bag_j, 0.0);
+            }
+        }
+    }
+
+    /// Checks every pheromone is still finite after an update, an
+    /// inf would make every selection wheel on that bag inf or NaN
+    /// guard: Whether overflowed edges are clamped or reported
+    fn guard_overflow(&mut self, guard: OverflowGuard) -> Result<(), GraphError> {
+        match guard {
+            OverflowGuard::Clamp => {
+                self.graph.tau.clamp_non_finite(MAX_PHEROMONE);
+                Ok(())
+            }
+            OverflowGuard::Error => match self.graph.tau.first_non_finite() {
+                Some((i, j)) => Err(GraphError::PheromoneOverflow {
+                    bag_i: self.graph.number_at(i),
+                    bag_j: self.graph.number_at(j),
+                }),
+                None => Ok(()),
             },
-            None => (),
         }
-        
-        // Evaporate edges
-        self.graph.evaporation_edges(evaporation_rate);
+    }
+
+    /// Adds the ants' deposits to the edges, and reinforces the
+    /// incumbent if the rule asks for it
+    fn deposit(&mut self, deposits: &Deposits, p_rate: f64, rule: &UpdateRule) {
+        for ((bag_i, bag_j), value) in deposits {
+            self.graph.tau.add_to_edge(*bag_i, *bag_j, *value);
+        }
 
-        // Update pheromone levels for all edges traversed by an ant
+        // Best-so-far reinforcement, the incumbent is deposited on
+        // once more on top of the normal per-ant deposit
+        if rule.reinforce_global_best && !self.incumbent.is_empty() {
+            let best = &self.incumbent;
+            for edge in best.edges() {
+                self.graph.deposit_phero(edge, best.cost, best.weight, p_rate, rule.deposit);
+            }
+        }
+    }
+
+    /// Adds the pheromones of all edges traversed by the depositing
+    /// tours to deposits, every ant's or only a best tour's
+    /// p_rate: Pheromone scalar
+    /// rule: The update rule, its source picks the tours and its deposit
+    ///     how much pheromone each tour deposits
+    fn deposit_tours(&self, deposits: &mut Deposits, p_rate: f64, rule: &UpdateRule) {
+        let best = match rule.source {
+            DepositSource::AllAnts => None,
+            DepositSource::IterationBest => Some(&self.best_path),
+            DepositSource::GlobalBest => Some(&self.incumbent),
+        };
+        if let Some(best) = best {
+            for edge in best.edges() {
+                add_deposit(deposits, edge, self.graph.deposit_amount(best.cost, best.weight, p_rate, rule.deposit));
+            }
+            return;
+        }
+        let rule = rule.deposit;
         for ant in self.ants.iter() {
             let tour_value: f64 = ant.calculate_tour_cost(&self.graph);
             let tour_weight: f64 = ant.calcluate_tour_weight(&self.graph);
-            let mut bag_i: usize = *ant.tour.get(0).unwrap();
-            // Skip first bag_i
-            for bag_j in ant.tour.iter().skip(1) {       
-                self.graph.deposit_phero((bag_i, *bag_j), tour_value, tour_weight, p_rate);                
-                bag_i = *bag_j
+            for edge in ant.edges() {
+                add_deposit(deposits, edge, self.graph.deposit_amount(tour_value, tour_weight, p_rate, rule));
             }
         }
     }
@@ -138,31 +720,193 @@ impl Colony {
         if !self.are_all_tours_finished() {
             return Some("Failed: Ants have not finished their tour".to_string());
         }
+        // Find the ant with the highest cost
+        let best = Tour::from(self.top_ant().unwrap());
         // Update the number of fitness evaluations by the number of ants, since
         // its one tour evaluation per ant tour
-        self.num_of_fitness_evaluations += self.ants.len() as i64;
-        // Find all the ants values
-        let ants_values: Vec<f64> = self.ants.iter().map(|ant| ant.current_cost).collect();
-        
-        // Find the ant with the highest cost
-        let top_ant: &Ant = self.ants
-            .get(ants_values
+        self.record_iteration(best, self.ants.len() as i64, self.calculate_average_cost());
+        // Succussful return
+        None
+    }
+
+    /// Returns the best tour found across all iterations, the tour the
+    /// results of a run are reported from
+    pub fn best_tour(&self) -> &Tour {
+        &self.global_best
+    }
+
+    /// Returns the ant with the highest scoring tour, None if there are no ants
+    fn top_ant(&self) -> Option<&Ant> {
+        let score = |ant: &Ant| self.objective.score(ant.current_cost, ant.tour.len(), ant.current_weight);
+        self.ants
             .iter()
-            .enumerate()
-            .max_by(|(_, a), (_, b)| a.partial_cmp(b)
+            .max_by(|a, b| score(a).partial_cmp(&score(b))
             .unwrap_or(Ordering::Equal))
-            .map(|(index, _)| index)
-            .unwrap())
-            .unwrap();        
-        
+    }
+
+    /// Records a finished iteration, setting the best tours, counting
+    /// its fitness evaluations and adding it to the convergence history
+    /// best: The iteration's best tour
+    /// evaluations: Number of tours evaluated in the iteration
+    /// average: Average tour cost of the iteration
+    fn record_iteration(&mut self, best: Tour, evaluations: i64, average: f64) {
+        // A repeated bag means the visited bag tracking is broken and the cost is double counted
+        debug_assert!(graph::first_duplicate(&best.bags).is_none(), "best tour visits a bag twice: {:?}", best.bags);
+        let (best, probes) = if self.local_search { self.swap_search(best) } else { (best, 0) };
+        self.num_of_fitness_evaluations += evaluations;
+        if self.count_local_search {
+            self.num_of_fitness_evaluations += probes;
+        }
         // Set the colony's best tour data
-        self.best_path = (
-            top_ant.tour.clone(),
-            top_ant.current_cost,
-            top_ant.current_weight,
-        );
-        // Succussful return
-        None
+        self.best_path = best;
+        // Keep track of the best tour across all iterations, only gains of
+        // at least min_improvement count as the search improving. The first
+        // tour always improves on the empty global best, whatever its score
+        let candidate = self.objective.score_tour(&self.best_path);
+        let gain = if self.global_best.is_empty() { f64::INFINITY } else { candidate - self.objective.score_tour(&self.global_best) };
+        if gain > 0.0 && gain >= self.min_improvement {
+            self.stagnation = 0;
+            self.last_improvement = self.num_of_fitness_evaluations;
+        } else {
+            self.stagnation += 1;
+        }
+        if gain > 0.0 {
+            self.global_best = self.best_path.clone();
+        }
+        // The policy only picks the incumbent the deposits reinforce, a
+        // worse tour it accepts leaves the global best as it is
+        let accept = match self.acceptance {
+            Some(policy) if !self.incumbent.is_empty() => {
+                policy(self.objective.score_tour(&self.incumbent), candidate, self.budget_fraction())
+            }
+            _ => gain > 0.0,
+        };
+        if accept {
+            self.incumbent = self.best_path.clone();
+        }
+        self.push_history(IterationResult {
+            iteration: self.history.last().map_or(1, |last| last.iteration + 1),
+            evaluations: self.num_of_fitness_evaluations,
+            iteration_best: self.best_path.cost,
+            global_best: self.global_best.cost,
+            average,
+            diversity: self.tour_metrics.then(|| self.diversity()),
+            entropy: self.tour_metrics.then(|| self.entropy()),
+        });
+    }
+
+    /// Improves a tour by item swaps, a bag in the tour is replaced by one
+    /// outside it whenever the swap fits and raises the tour's score,
+    /// until no swap does. Each swap tried is one probe, the evaluation
+    /// of a neighbouring tour. Multi-knapsack tours are left as they are,
+    /// as a tour does not record which van each bag went in
+    /// Returns the improved tour and the number of probes
+    pub fn swap_search(&self, mut tour: Tour) -> (Tour, i64) {
+        if self.graph.capacities.len() > 1 {
+            return (tour, 0);
+        }
+        let mut probes: i64 = 0;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            'search: for position in 0..tour.len() {
+                let removed = &self.graph.graph[tour.bags[position]];
+                for bag in 0..self.graph.nodes {
+                    if tour.bags.contains(&bag) {
+                        continue;
+                    }
+                    probes += 1;
+                    let added = &self.graph.graph[bag];
+                    let cost = tour.cost - removed.cost + added.cost;
+                    let weight = tour.weight - removed.weight + added.weight;
+                    if self.graph.within_weight(weight, self.graph.max_weight())
+                        && self.objective.score(cost, tour.len(), weight) > self.objective.score_tour(&tour) {
+                        tour = Tour { cost, weight, ..tour };
+                        tour.bags[position] = bag;
+                        improved = true;
+                        break 'search;
+                    }
+                }
+            }
+        }
+        (tour, probes)
+    }
+
+    /// Returns the fraction of the fitness budget used so far, capped at
+    /// 1, or 0 if the colony has no budget
+    fn budget_fraction(&self) -> f64 {
+        match self.fitness_budget {
+            Some(budget) if budget > 0 => (self.num_of_fitness_evaluations as f64 / budget as f64).min(1.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Adds a point to the history, thinning it when it outgrows the cap.
+    /// The latest point is always kept, so it is dropped when the next
+    /// point arrives if it does not fall on the stride
+    fn push_history(&mut self, point: IterationResult) {
+        let cap = match self.history_cap {
+            Some(cap) => cap.max(1),
+            None => return self.history.push(point),
+        };
+        let on_stride = |point: &IterationResult, stride: usize| (point.iteration - 1).is_multiple_of(stride);
+        if self.history.last().is_some_and(|last| !on_stride(last, self.history_stride)) {
+            self.history.pop();
+        }
+        self.history.push(point);
+        if self.history.len() > 2 * cap {
+            self.history_stride *= 2;
+            let stride = self.history_stride;
+            let last = self.history.len() - 1;
+            let mut index = 0;
+            self.history.retain(|point| {
+                index += 1;
+                index - 1 == last || on_stride(point, stride)
+            });
+        }
+    }
+
+    /// Thins the history down to exactly history_cap points, evenly
+    /// spaced across the kept iterations so the shape of the curve is
+    /// kept. The first and latest iterations are always kept. Does
+    /// nothing without a cap or when the history is within it
+    pub fn compact_history(&mut self) {
+        let cap = match self.history_cap {
+            Some(cap) if self.history.len() > cap => cap,
+            _ => return,
+        };
+        let last = self.history.len() - 1;
+        self.history = match cap {
+            0 => Vec::new(),
+            1 => vec![self.history[last]],
+            _ => (0..cap).map(|i| self.history[i * last / (cap - 1)]).collect(),
+        };
+    }
+
+    /// Excludes everything run so far from the colony's statistics,
+    /// while keeping the pheromones it left. The fitness evaluations,
+    /// convergence history and best tours are reset, so a warmup
+    /// search only seeds the pheromones
+    pub fn discard_warmup(&mut self) {
+        self.num_of_fitness_evaluations = 0;
+        self.stagnation = 0;
+        self.last_improvement = 0;
+        self.history.clear();
+        self.history_stride = 1;
+        self.best_path = Tour::default();
+        self.global_best = Tour::default();
+        self.incumbent = Tour::default();
+    }
+
+    /// Sets the fitness evaluation, stagnation, last improvement and
+    /// bag selection counters back to zero, so the colony can be reused
+    /// for another trial. The graph, pheromones, ants and best tours
+    /// are left as they are
+    pub fn reset_counters(&mut self) {
+        self.num_of_fitness_evaluations = 0;
+        self.stagnation = 0;
+        self.last_improvement = 0;
+        self.bag_selections.iter_mut().for_each(|count| *count = 0);
     }
 
     /// Checks if all ants tours are finished by checking if 
@@ -170,11 +914,7 @@ impl Colony {
     /// Returns false if there are still ants with nodes left to visit
     /// if no ants cannot continue, returns true
     pub fn are_all_tours_finished(&self) -> bool {
-        !self.ants.iter().any(|ant| !self.graph.get_availible_bags(
-            &ant.current_bag, 
-            &ant.tour, 
-            ant.calculate_allowed_weight(self.graph.max_weight)
-        ).is_empty())
+        self.ants.iter().all(|ant| ant.is_finished(&self.graph))
     }
 
     /// Gets the average tour as the cost of 
@@ -188,6 +928,135 @@ impl Colony {
     pub fn calculate_total_colony_cost(&self) -> f64{
         self.ants.iter().map(|ant|ant.current_cost).sum()
     }
+
+    /// Measures how different the ants' tours are, as the mean Jaccard
+    /// distance between the bags of every pair of tours. 0 means every
+    /// ant built the same tour, 1 means no two tours share a bag. Two
+    /// empty tours are the same tour.
+    /// Returns 0 with fewer than two ants
+    pub fn diversity(&self) -> f64 {
+        if self.ants.len() < 2 {
+            return 0.0;
+        }
+        let mut in_tour: Vec<bool> = vec![false; self.graph.nodes];
+        let mut total_distance: f64 = 0.0;
+        let mut pairs: f64 = 0.0;
+        for (i, ant) in self.ants.iter().enumerate() {
+            ant.tour.iter().for_each(|bag| in_tour[*bag] = true);
+            for other in self.ants.iter().skip(i + 1) {
+                let shared = other.tour.iter().filter(|bag| in_tour[**bag]).count() as f64;
+                let union = (ant.tour.len() + other.tour.len()) as f64 - shared;
+                if union > 0.0 {
+                    total_distance += 1.0 - shared / union;
+                }
+                pairs += 1.0;
+            }
+            ant.tour.iter().for_each(|bag| in_tour[*bag] = false);
+        }
+        total_distance / pairs
+    }
+
+    /// Measures how evenly the ants' tours spread over the bags, as the
+    /// Shannon entropy in bits of how often each bag appears across the
+    /// tours. 0 means every tour uses the same single bag, log2 of the
+    /// number of bags means every bag appears equally often.
+    /// Returns 0 when no ant has a bag
+    pub fn entropy(&self) -> f64 {
+        let mut counts: Vec<usize> = vec![0; self.graph.nodes];
+        self.ants.iter().flat_map(|ant| ant.tour.iter()).for_each(|bag| counts[*bag] += 1);
+        let total: usize = counts.iter().sum();
+        counts
+            .iter()
+            .filter(|count| **count > 0)
+            .map(|count| {
+                let p = *count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Runs one full iteration: places num_of_ants new ants, builds
+    /// their tours and updates the pheromones
+    /// start_strategy: Where the new ants start
+    /// alpha: Scalar value applied to pheromone levels
+    /// evaporation_rate: Evaporation scalar
+    /// p_rate: Pheromone scalar
+    /// rule: How the update is applied, see UpdateRule
+    /// Returns the iteration's metrics, as added to the history, or a
+    /// GraphError if the pheromone update fails
+    pub fn iterate(
+        &mut self,
+        num_of_ants: i64,
+        start_strategy: StartStrategy,
+        alpha: f64,
+        evaporation_rate: f64,
+        p_rate: f64,
+        rule: &UpdateRule,
+    ) -> Result<IterationResult, GraphError> {
+        self.init_ants(num_of_ants, start_strategy);
+        self.run_tours(alpha, None);
+        self.update_edges(evaporation_rate, p_rate, rule)?;
+        Ok(*self.history.last().expect("update_edges records the iteration"))
+    }
+
+    /// Counts the distinct edges of the global best tour, and how many
+    /// of them are strong, with more pheromone than the threshold, to
+    /// show whether the best tour rides the high pheromone edges
+    /// threshold: Pheromone an edge needs above it to count as strong
+    /// Returns (total edges, strong edges)
+    pub fn best_tour_edge_stats(&self, threshold: f64) -> (usize, usize) {
+        let mut edges: Vec<(usize, usize)> = self.global_best
+            .edges()
+            .map(|(bag_i, bag_j)| (bag_i.min(bag_j), bag_i.max(bag_j)))
+            .collect();
+        edges.sort();
+        edges.dedup();
+        let strong = edges.iter().filter(|(i, j)| self.graph.tau.get_edge(*i, *j) > threshold).count();
+        (edges.len(), strong)
+    }
+
+    /// Returns how many times each bag has been added to an ant's tour,
+    /// across all ants and iterations, indexed as the graph
+    pub fn bag_selection_frequencies(&self) -> &[u64] {
+        &self.bag_selections
+    }
+
+    /// Explains the global best tour bag by bag. Each bag is removed in
+    /// turn and the freed capacity refilled with the most valuable unused
+    /// bag that fits, if any, to see how much the tour relies on the bag
+    /// Returns (bag index in the graph, marginal contribution, cost of the
+    /// tour without the bag) for each bag in tour order, where the marginal
+    /// contribution is the tour's cost minus the cost without the bag
+    pub fn best_tour_bag_contributions(&self) -> Vec<(usize, f64, f64)> {
+        let best = &self.global_best;
+        best.bags
+            .iter()
+            .map(|bag| {
+                let removed = &self.graph.graph[*bag];
+                let free_weight = self.graph.max_weight() - (best.weight - removed.weight);
+                let replacement = (0..self.graph.nodes)
+                    .filter(|other| other != bag && !best.bags.contains(other))
+                    .filter(|other| self.graph.within_weight(self.graph.graph[*other].weight, free_weight))
+                    .map(|other| self.graph.graph[other].cost)
+                    .fold(0.0, f64::max);
+                let cost_without = best.cost - removed.cost + replacement;
+                (*bag, best.cost - cost_without, cost_without)
+            })
+            .collect()
+    }
+
+    /// Returns the n most selected bags as (bag number, selection count),
+    /// most selected first
+    pub fn top_selected_bags(&self, n: usize) -> Vec<(i64, u64)> {
+        let mut frequencies: Vec<(i64, u64)> = self.bag_selections
+            .iter()
+            .enumerate()
+            .map(|(bag, count)| (self.graph.number_at(bag), *count))
+            .collect();
+        frequencies.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        frequencies.truncate(n);
+        frequencies
+    }
 }
 
 /// Represents an Ant and it's meta information
@@ -195,6 +1064,10 @@ impl Colony {
 /// tour: Vector of index's of bags in graph
 /// current_cost: The current, cumulative cost of all bags in the tour
 /// current_weight: The current, cumulative weight of all bags in the tour
+/// filled_vans: Weight of each van the ant has filled and moved on from,
+///     for multi-knapsack problems. The van being filled is the next one,
+///     so a single van problem never fills any, see Graph::capacities
+#[derive(Debug, Clone)]
 pub struct Ant {
     pub current_bag: usize,
     pub tour: Vec<usize>,
@@ -202,6 +1075,7 @@ pub struct Ant {
     // small cost of memory
     pub current_cost: f64,
     pub current_weight: f64,
+    pub filled_vans: Vec<f64>,
 }
 
 impl Ant {
@@ -212,7 +1086,8 @@ impl Ant {
             current_bag: bag, 
             tour: vec![bag], 
             current_cost: graph.graph[bag].cost, 
-            current_weight: graph.graph[bag].weight
+            current_weight: graph.graph[bag].weight,
+            filled_vans: Vec::new(),
         }
     }
 
@@ -222,27 +1097,58 @@ impl Ant {
     /// working within weight constraints
     /// graph: Graph struct reference containing bags
     /// alpha: Scalar value applied to pheromone levels
-    pub fn update_ant(&mut self, graph: &Graph, alpha: f64) {
+    /// bag_selections: Selection counter, incremented for the added bag
+    /// rng: Random number generator used to select the bag
+    /// Returns the number of bags the ant could choose from, 0 if its
+    /// tour is finished
+    pub fn update_ant<R: Rng>(&mut self, graph: &Graph, alpha: f64, bag_selections: &mut [u64], rng: &mut R) -> usize {
         // Gets all valid bags the ant can move too
-        let availible_bags: Vec<usize> = graph.get_availible_bags(
-            &self.current_bag,
-            &self.tour,
-            self.calculate_allowed_weight(graph.max_weight)
-        );        
+        let (van, availible_bags) = self.availible_bags(graph);
         // If there is atleast one bag availible, add a bag to the ant's tour
         // according to the update rules in graph.select_path
         if !availible_bags.is_empty() {        
-            let new_bag = graph.select_path(&self.current_bag, &availible_bags, alpha);                
-            if new_bag.is_some() { 
-                let new_bag = new_bag.unwrap();
+            let new_bag = graph.select_path(&self.current_bag, &availible_bags, alpha, rng);                
+            if let Some(new_bag) = new_bag { 
+                // Close the vans no bag fits in any more
+                while self.filled_vans.len() < van {
+                    self.filled_vans.push(self.van_weight());
+                }
                 self.tour.push(new_bag);
                 self.current_bag = new_bag;
                 self.current_cost += graph.graph[self.current_bag].cost;
                 self.current_weight += graph.graph[self.current_bag].weight;
+                bag_selections[new_bag] += 1;
             }
         }
+        availible_bags.len()
     }
     
+    /// Returns the bag the ant is most likely to move to next, the one
+    /// with the highest probability on its selection wheel, without
+    /// moving the ant. Ties go to the lowest index. For step by step
+    /// explanations of a tour
+    /// graph: Graph struct reference containing bags
+    /// alpha: Scalar value applied to pheromone levels
+    /// Returns None if no bag fits in the ant's tour
+    pub fn best_next_bag(&self, graph: &Graph, alpha: f64) -> Option<usize> {
+        let (_, availible_bags) = self.availible_bags(graph);
+        graph
+            .debug_wheel(self.current_bag, &availible_bags, alpha)
+            .into_iter()
+            .fold(None, |best: Option<(usize, f64)>, (bag, probability, _)| match best {
+                Some((_, best_probability)) if best_probability >= probability => best,
+                _ => Some((bag, probability)),
+            })
+            .map(|(bag, _)| bag)
+    }
+
+    /// Returns the edges the ant traversed, as (from, to) pairs of
+    /// consecutive bags in its tour, in the order they were walked.
+    /// Every deposit walks a tour's edges through this
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        edges(&self.tour)
+    }
+
     /// Get the ant's total tour cost
     pub fn calculate_tour_cost(&self, graph: &Graph) -> f64{
         self.tour.iter().map(|bag| graph.graph[*bag].cost).sum()
@@ -253,17 +1159,46 @@ impl Ant {
         self.tour.iter().map(|bag| graph.graph[*bag].weight).sum()
     }
     
-    /// Get the allowed weight by the difference in the 
-    /// max_allowed weight and the ant's current tour weight
-    pub fn calculate_allowed_weight(&self, max_allowed_weight: f64) -> f64 {
-        max_allowed_weight - self.current_weight
+    /// Get the allowed weight by the difference in the capacity
+    /// of the van being filled and the weight already in it
+    pub fn calculate_allowed_weight(&self, graph: &Graph) -> f64 {
+        graph.capacities[self.filled_vans.len()] - self.van_weight()
+    }
+
+    /// Returns the weight in the van being filled
+    fn van_weight(&self) -> f64 {
+        self.current_weight - self.filled_vans.iter().sum::<f64>()
+    }
+
+    /// Gets the bags the ant can add next, those that fit in the van
+    /// being filled or, once none does, in the first later van any
+    /// unused bag fits in
+    /// graph: Graph struct reference containing bags and capacities
+    /// Returns (index of the van the bags go in, the bags), with no
+    /// bags once the tour is finished
+    pub fn availible_bags(&self, graph: &Graph) -> (usize, Vec<usize>) {
+        let van = self.filled_vans.len();
+        let current = (van, graph.get_availible_bags(&self.current_bag, &self.tour, self.calculate_allowed_weight(graph)));
+        if !current.1.is_empty() {
+            return current;
+        }
+        // Later vans are still empty
+        (van + 1..graph.capacities.len())
+            .map(|van| (van, graph.get_availible_bags(&self.current_bag, &self.tour, graph.capacities[van])))
+            .find(|(_, bags)| !bags.is_empty())
+            .unwrap_or(current)
+    }
+
+    /// Returns true if no unused bag fits in any van the ant has left
+    pub fn is_finished(&self, graph: &Graph) -> bool {
+        self.availible_bags(graph).1.is_empty()
     }
 
     /// Prints the ant's tour in a human-readable format
     pub fn print_ants_tour(&self, graph: &Graph) {
         println!("___________________");
         for bag in self.tour.iter() {
-            print!("{} -> ", graph.graph[*bag].number);
+            print!("{} -> ", graph.number_at(*bag));
         }
         println!("Total Cost: {}", self.calculate_tour_cost(graph));
         println!("Length: {}", self.tour.len());
@@ -275,10 +1210,62 @@ impl Ant {
 #[cfg(test)]
 mod test {
     use std::cmp::Ordering;
+    use super::*;
+    use crate::graph::Bag;
+
+    /// Builds a small graph of four bags where any two bags fill the capacity
+    fn small_graph() -> Graph {
+        let graph: Vec<Bag> = (0..4)
+            .map(|number| Bag { number, weight: 10.0, cost: 10.0, ratio: 1.0, h: 1.0 })
+            .collect();
+        Graph::from_bags(20.0, graph)
+    }
+
+    /// Builds a colony over the small graph with two finished ants
+    fn finished_colony() -> Colony {
+        let graph = small_graph();
+        let mut first = Ant::birth(0, &graph);
+        first.tour.push(1);
+        first.current_bag = 1;
+        first.current_cost += 10.0;
+        first.current_weight += 10.0;
+        let mut second = Ant::birth(2, &graph);
+        second.tour.push(3);
+        second.current_bag = 3;
+        second.current_cost += 10.0;
+        second.current_weight += 10.0;
+        Colony::from_graph_and_ants(graph, vec![first, second])
+    }
+
+    /// Runs iterations of the colony with the default update rule
+    fn run_iterations<R: Rng>(colony: &mut Colony<R>, num_of_ants: i64, iterations: usize, start_strategy: StartStrategy) {
+        for _ in 0..iterations {
+            colony.iterate(num_of_ants, start_strategy, 1.0, 0.1, 1.0, &UpdateRule::default()).unwrap();
+        }
+    }
+
+    /// Tests the incumbent's edges only get the extra deposit when reinforcement is enabled
+    #[test]
+    fn reinforce_global_best() {
+        let mut colony = finished_colony();
+        colony.global_best = Tour { bags: vec![0, 2], cost: 100.0, weight: 20.0 };
+        colony.incumbent = colony.global_best.clone();
+        colony.update_edges(0.0, 1.0, &UpdateRule { reinforce_global_best: true, ..Default::default() }).unwrap();
+        assert_eq!(colony.graph.tau.get_edge(0, 2), 100.0 / 20.0);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), 20.0 / 20.0);
+
+        let mut colony = finished_colony();
+        colony.global_best = Tour { bags: vec![0, 2], cost: 100.0, weight: 20.0 };
+        colony.incumbent = colony.global_best.clone();
+        colony.update_edges(0.0, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(colony.graph.tau.get_edge(0, 2), 0.0);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), 20.0 / 20.0);
+    }
+
     /// Test the Ordering of finding the best ant out of a selection of "tour" values
     #[test]
     fn test_f64_order() {
-        let ants_values = vec![0.0, 32000.32, 16.4, 100.0, 11.0];
+        let ants_values = [0.0, 32000.32, 16.4, 100.0, 11.0];
         let top_index: usize = ants_values
             .iter()
             .enumerate()
@@ -288,4 +1275,610 @@ mod test {
             .unwrap();
         assert_eq!(top_index, 1);
     }    
-}
\ No newline at end of file
+
+    /// Tests the selection counter counts a bag added to several ants' tours
+    #[test]
+    fn bag_selection_frequencies() {
+        let mut graph = small_graph();
+        graph.graph[2].weight = 50.0;
+        graph.graph[3].weight = 50.0;
+        let mut colony = Colony::new(graph);
+        // From bag 0 only bag 1 fits, so every ant must select it
+        colony.ants = (0..3).map(|_| Ant::birth(0, &colony.graph)).collect();
+        colony.run_tours(1.0, None);
+        assert_eq!(colony.bag_selection_frequencies(), &[0, 3, 0, 0]);
+        assert_eq!(colony.top_selected_bags(1), vec![(1, 3)]);
+    }
+
+    /// Tests the best tour and average of a colony built from known ants
+    #[test]
+    fn from_graph_and_ants() {
+        let mut colony = finished_colony();
+        colony.ants[1].current_cost = 30.0;
+        assert_eq!(colony.set_best_tour(), None);
+        assert_eq!(colony.best_path, Tour { bags: vec![2, 3], cost: 30.0, weight: 20.0 });
+        assert_eq!(colony.global_best, colony.best_path);
+        assert_eq!(colony.calculate_average_cost(), 25.0);
+        assert_eq!(colony.num_of_fitness_evaluations, 2);
+    }
+
+    /// Tests ants start on the highest ratio bags under the top ratio strategy
+    #[test]
+    fn top_ratio_start() {
+        let mut graph = small_graph();
+        graph.graph[1].ratio = 3.0;
+        graph.graph[3].ratio = 2.0;
+        let mut colony = Colony::from_graph_and_ants(graph, Vec::new());
+        colony.init_ants(3, StartStrategy::TopRatio);
+        let starts: Vec<usize> = colony.ants.iter().map(|ant| ant.current_bag).collect();
+        assert_eq!(starts, vec![1, 3, 0]);
+    }
+
+    /// Tests a colony over a graph with no bags spawns no ants and finds an empty tour
+    #[test]
+    fn empty_graph() {
+        for start_strategy in [StartStrategy::Random, StartStrategy::TopRatio] {
+            let mut colony = Colony::new(Graph::from_bags(10.0, vec![]));
+            colony.init_ants(4, start_strategy);
+            assert!(colony.ants.is_empty());
+            assert_eq!(colony.run_tours(1.0, None), Tour::default());
+        }
+    }
+
+    /// Tests the pheromone left by each update order and number of passes
+    #[test]
+    fn update_order() {
+        let update = |order: UpdateOrder, passes: usize| {
+            let mut colony = finished_colony();
+            colony.graph.tau.set_edge(0, 1, 1.0);
+            colony.update_edges(0.5, 1.0, &UpdateRule { order, passes, ..Default::default() }).unwrap();
+            colony.graph.tau.get_edge(0, 1)
+        };
+        // Each update deposits 20 / 20 = 1 on edge 0-1
+        assert_eq!(update(UpdateOrder::EvaporateThenDeposit, 1), 1.0 * 0.5 + 1.0);
+        assert_eq!(update(UpdateOrder::DepositThenEvaporate, 1), (1.0 + 1.0) * 0.5);
+        assert_eq!(update(UpdateOrder::EvaporateThenDeposit, 2), (1.0 * 0.5 + 1.0) * 0.5 + 1.0);
+    }
+
+    /// Tests the diversity of identical, partly shared and disjoint tours
+    #[test]
+    fn diversity() {
+        let mut colony = finished_colony();
+        assert_eq!(colony.diversity(), 1.0);
+        colony.ants[1].tour = vec![0, 1];
+        assert_eq!(colony.diversity(), 0.0);
+        colony.ants[1].tour = vec![1, 2];
+        assert_eq!(colony.diversity(), 1.0 - 1.0 / 3.0);
+        colony.ants.iter_mut().for_each(|ant| ant.tour.clear());
+        assert_eq!(colony.diversity(), 0.0);
+    }
+
+    /// Tests a discarded warmup leaves its pheromones but not its evaluations, score or history
+    #[test]
+    fn discard_warmup() {
+        let mut colony = finished_colony();
+        colony.update_edges(0.1, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(colony.history.len(), 1);
+        assert_eq!(colony.history[0].evaluations, 2);
+        assert_eq!(colony.history[0].global_best, 20.0);
+        let seeded = colony.graph.tau.get_edge(0, 1);
+        assert!(seeded > 0.0);
+
+        colony.discard_warmup();
+        assert_eq!(colony.num_of_fitness_evaluations, 0);
+        assert!(colony.history.is_empty());
+        assert_eq!(colony.global_best, Tour::default());
+        assert_eq!(colony.graph.tau.get_edge(0, 1), seeded);
+
+        colony.update_edges(0.1, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(colony.history[0].evaluations, 2);
+    }
+
+    /// Tests the colony's ants fill van after van without sharing a bag or overfilling a van
+    #[test]
+    fn multi_knapsack() {
+        let bags: Vec<Bag> = (0..12)
+            .map(|number| Bag { number, weight: 5.0 + number as f64, cost: 10.0, ratio: 10.0 / (5.0 + number as f64), h: 1.0 })
+            .collect();
+        let mut graph = Graph::from_bags(30.0, bags);
+        graph.capacities = vec![30.0, 20.0];
+        let mut colony = Colony::with_rng(graph, StdRng::seed_from_u64(5));
+        for _ in 0..20 {
+            colony.iterate(4, StartStrategy::Random, 1.0, 0.1, 1.0, &UpdateRule::default()).unwrap();
+            for ant in &colony.ants {
+                assert!(graph::first_duplicate(&ant.tour).is_none());
+                assert!(ant.is_finished(&colony.graph));
+                // The last van is filled once every van before it is
+                assert_eq!(ant.filled_vans.len(), 1);
+                let van_weights = [ant.filled_vans[0], ant.current_weight - ant.filled_vans[0]];
+                for (weight, capacity) in van_weights.iter().zip(&colony.graph.capacities) {
+                    assert!(*weight > 0.0 && weight <= capacity);
+                }
+                assert_eq!(ant.current_weight, ant.calcluate_tour_weight(&colony.graph));
+            }
+        }
+        // Both vans count towards the best tour, which a single van cannot hold
+        assert!(colony.global_best.weight > 30.0);
+        assert!(colony.global_best.is_feasible(&colony.graph));
+    }
+
+    /// Tests building the ants in batches leaves the same pheromones as building them all at once
+    #[test]
+    fn batched_tours() {
+        // Pheromone only lies on edges 0-1 and 2-3, so tours are fixed by the start bag
+        let batched_colony = || {
+            let mut colony = Colony::from_graph_and_ants(small_graph(), Vec::new());
+            colony.graph.tau.set_edge(0, 1, 1.0);
+            colony.graph.tau.set_edge(2, 3, 2.0);
+            colony
+        };
+        let rule = UpdateRule { passes: 2, ..Default::default() };
+
+        let mut all_at_once = batched_colony();
+        all_at_once.init_ants(4, StartStrategy::TopRatio);
+        all_at_once.run_tours(1.0, None);
+        all_at_once.update_edges(0.1, 1.0, &rule).unwrap();
+
+        let mut batched = batched_colony();
+        let deposits = batched.run_batched_tours(4, 3, 1.0, StartStrategy::TopRatio, 1.0, &rule);
+        batched.apply_update(0.1, 1.0, &deposits, &rule).unwrap();
+        assert_eq!(batched.ants.len(), 1);
+        // Only the edges the tours walked are held, not a matrix of every edge
+        let mut edges: Vec<(usize, usize)> = deposits.keys().copied().collect();
+        edges.sort();
+        assert_eq!(edges, vec![(0, 1), (2, 3)]);
+
+        for i in 0..4 {
+            for j in i+1..4 {
+                assert!((batched.graph.tau.get_edge(i, j) - all_at_once.graph.tau.get_edge(i, j)).abs() < 1e-12);
+            }
+        }
+        assert!(all_at_once.graph.tau.get_edge(0, 1) > 1.0);
+        assert_eq!(batched.num_of_fitness_evaluations, all_at_once.num_of_fitness_evaluations);
+        // Diversity and entropy differ, as the batched colony only keeps its last batch of ants
+        let scores = |colony: &Colony| colony.history.iter()
+            .map(|point| (point.iteration, point.evaluations, point.iteration_best, point.global_best, point.average))
+            .collect::<Vec<_>>();
+        assert_eq!(scores(&batched), scores(&all_at_once));
+    }
+
+    /// Tests the tour's accessors match the ant it was taken from
+    #[test]
+    fn tour_accessors() {
+        let mut graph = small_graph();
+        graph.graph[2].number = 7;
+        let colony = finished_colony();
+        let tour = Tour::from(&colony.ants[1]);
+        assert_eq!(tour, Tour { bags: vec![2, 3], cost: 20.0, weight: 20.0 });
+        assert_eq!(tour.len(), 2);
+        assert_eq!(tour.bag_numbers(&graph), vec![7, 3]);
+        assert!(tour.is_feasible(&graph));
+        graph.capacities[0] = 10.0;
+        assert!(!tour.is_feasible(&graph));
+        assert!(Tour::default().is_empty());
+    }
+
+    /// Tests the slack a tour leaves is reported along with whether an unused bag fits in it
+    #[test]
+    fn capacity_slack() {
+        let mut graph = small_graph();
+        graph.capacities[0] = 25.0;
+        graph.graph[3].weight = 5.0;
+        // Bag 3 fits in the 5 left by the first two bags
+        let tour = Tour { bags: vec![0, 1], cost: 20.0, weight: 20.0 };
+        assert_eq!(tour.capacity_slack(&graph), (5.0, true));
+        let full = Tour { bags: vec![0, 1, 3], cost: 30.0, weight: 25.0 };
+        assert_eq!(full.capacity_slack(&graph), (0.0, false));
+        graph.graph[3].weight = 6.0;
+        assert_eq!(tour.capacity_slack(&graph), (5.0, false));
+    }
+
+    /// Tests a gain below the minimum improvement does not reset the stagnation counter
+    #[test]
+    fn min_improvement() {
+        let mut colony = finished_colony();
+        colony.min_improvement = 1.0;
+        assert_eq!(colony.set_best_tour(), None);
+        assert_eq!((colony.stagnation, colony.last_improvement), (0, 2));
+
+        colony.ants[0].current_cost = 20.5;
+        assert_eq!(colony.set_best_tour(), None);
+        assert_eq!(colony.global_best.cost, 20.5);
+        assert_eq!((colony.stagnation, colony.last_improvement), (1, 2));
+
+        colony.ants[0].current_cost = 22.0;
+        assert_eq!(colony.set_best_tour(), None);
+        assert_eq!((colony.stagnation, colony.last_improvement), (0, 6));
+    }
+
+    /// Tests a cloned ant's tour is independent of the original's
+    #[test]
+    fn clone_ant() {
+        let colony = finished_colony();
+        let original = &colony.ants[0];
+        let mut clone = original.clone();
+        clone.tour.push(2);
+        clone.current_cost += 10.0;
+        assert_eq!(original.tour, vec![0, 1]);
+        assert_eq!(original.current_cost, 20.0);
+
+        let mut cloned_colony = colony.clone();
+        cloned_colony.ants[1].tour.clear();
+        cloned_colony.graph.tau.set_edge(0, 1, 5.0);
+        assert_eq!(colony.ants[1].tour, vec![2, 3]);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), 0.0);
+    }
+
+    /// Tests resetting the counters zeroes them without touching the graph
+    #[test]
+    fn reset_counters() {
+        let mut colony = finished_colony();
+        colony.bag_selections = vec![1, 2, 0, 3];
+        colony.update_edges(0.1, 1.0, &UpdateRule::default()).unwrap();
+        colony.stagnation = 4;
+        let pheromone = colony.graph.tau.get_edge(0, 1);
+
+        colony.reset_counters();
+        assert_eq!(colony.num_of_fitness_evaluations, 0);
+        assert_eq!((colony.stagnation, colony.last_improvement), (0, 0));
+        assert_eq!(colony.bag_selection_frequencies(), &[0, 0, 0, 0]);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), pheromone);
+        assert_eq!(colony.graph.nodes, 4);
+    }
+
+    /// Tests a best tour with a repeated bag is flagged
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "best tour visits a bag twice")]
+    fn duplicate_best_tour() {
+        let mut colony = finished_colony();
+        colony.ants[0].tour = vec![0, 1, 0];
+        colony.ants[0].current_cost = 30.0;
+        colony.set_best_tour();
+    }
+
+    /// Tests the objective's weights decide which of fixed tours is the best
+    #[test]
+    fn multi_objective() {
+        let best_with = |objective: MultiObjective| {
+            let mut colony = finished_colony();
+            // A costly, heavy tour of three bags against a cheaper single bag
+            colony.ants[0].tour = vec![0, 1, 2];
+            colony.ants[0].current_cost = 30.0;
+            colony.ants[0].current_weight = 30.0;
+            colony.ants[1].tour = vec![3];
+            colony.ants[1].current_cost = 28.0;
+            colony.objective = objective;
+            colony.set_best_tour();
+            colony.global_best.bags
+        };
+        assert_eq!(best_with(MultiObjective::default()), vec![0, 1, 2]);
+        assert_eq!(best_with(MultiObjective { count_weight: -5.0, ..Default::default() }), vec![3]);
+        assert_eq!(best_with(MultiObjective { weight_weight: -0.1, ..Default::default() }), vec![0, 1, 2]);
+        assert_eq!(best_with(MultiObjective { weight_weight: -0.5, ..Default::default() }), vec![3]);
+    }
+
+    /// Tests colonies built on two different generators both produce feasible runs
+    #[test]
+    fn rng_backends() {
+        fn run<R: Rng>(mut colony: Colony<R>) {
+            run_iterations(&mut colony, 6, 3, StartStrategy::Random);
+            assert_eq!(colony.history.len(), 3);
+            assert!(colony.global_best.cost > 0.0);
+            assert!(colony.global_best.is_feasible(&colony.graph));
+        }
+        run(Colony::with_rng(small_graph(), StdRng::seed_from_u64(7)));
+        run(Colony::with_rng(small_graph(), rand::rngs::SmallRng::seed_from_u64(7)));
+    }
+
+    /// Tests an overflowing deposit is clamped or reported instead of leaving inf on the edge
+    #[test]
+    fn pheromone_overflow() {
+        let overflow = |guard: OverflowGuard| {
+            let mut colony = finished_colony();
+            colony.graph.tau.set_edge(0, 1, f64::MAX);
+            let result = colony.update_edges(0.0, f64::MAX, &UpdateRule { overflow: guard, ..Default::default() });
+            (result, colony.graph.tau.get_edge(0, 1))
+        };
+        assert_eq!(overflow(OverflowGuard::Clamp), (Ok(()), MAX_PHEROMONE));
+        let (result, _) = overflow(OverflowGuard::Error);
+        assert_eq!(result, Err(GraphError::PheromoneOverflow { bag_i: 0, bag_j: 1 }));
+        assert!(result.unwrap_err().to_string().contains("MMAS"));
+    }
+
+    /// Tests one iteration on a small graph fills every field of its result
+    #[test]
+    fn iteration_result() {
+        let mut colony = Colony::new(small_graph());
+        colony.tour_metrics = true;
+        let result = colony.iterate(2, StartStrategy::TopRatio, 1.0, 0.1, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(colony.history, vec![result]);
+        assert_eq!(result.iteration, 1);
+        assert_eq!(result.evaluations, 2);
+        // Every bag is the same, so both tours hold two bags and fill the capacity
+        assert_eq!(result.iteration_best, 20.0);
+        assert_eq!(result.global_best, 20.0);
+        assert_eq!(result.average, 20.0);
+        assert_eq!(result.diversity, Some(colony.diversity()));
+        assert!((0.0..=1.0).contains(&colony.diversity()));
+        assert_eq!(result.entropy, Some(colony.entropy()));
+        assert!(colony.entropy() >= 1.0 && colony.entropy() <= 2.0);
+
+        // Without the tour metrics they are left out of the history
+        colony.tour_metrics = false;
+        let second = colony.iterate(2, StartStrategy::TopRatio, 1.0, 0.1, 1.0, &UpdateRule::default()).unwrap();
+        assert_eq!(second.iteration, 2);
+        assert_eq!(second.evaluations, 4);
+        assert_eq!((second.diversity, second.entropy), (None, None));
+    }
+
+    /// Tests a capped history of a 1000 iteration run keeps exactly 100 evenly spread points
+    #[test]
+    fn history_cap() {
+        let mut colony = Colony::new(small_graph());
+        colony.history_cap = Some(100);
+        for _ in 0..10 {
+            run_iterations(&mut colony, 2, 100, StartStrategy::TopRatio);
+            assert!(colony.history.len() <= 200);
+        }
+        colony.compact_history();
+        assert_eq!(colony.history.len(), 100);
+        assert_eq!(colony.history[0].iteration, 1);
+        assert_eq!(colony.history[99].iteration, 1000);
+        assert_eq!(colony.history[99].evaluations, 2000);
+        let gaps: Vec<usize> = colony.history.windows(2).map(|pair| pair[1].iteration - pair[0].iteration).collect();
+        assert!(gaps.iter().all(|gap| (5..=20).contains(gap)));
+
+        // Uncapped histories keep every iteration
+        let mut colony = Colony::new(small_graph());
+        run_iterations(&mut colony, 2, 300, StartStrategy::TopRatio);
+        colony.compact_history();
+        assert_eq!(colony.history.len(), 300);
+    }
+
+    /// Tests the best tour's edges are counted once and only strong ones pass the threshold
+    #[test]
+    fn best_tour_edge_stats() {
+        let mut colony = finished_colony();
+        assert_eq!(colony.best_tour_edge_stats(0.0), (0, 0));
+        colony.global_best = Tour { bags: vec![0, 1, 2, 3], cost: 40.0, weight: 40.0 };
+        colony.graph.tau.set_edge(0, 1, 5.0);
+        colony.graph.tau.set_edge(1, 2, 0.5);
+        colony.graph.tau.set_edge(2, 3, 2.0);
+        colony.graph.tau.set_edge(0, 3, 9.0);
+        assert_eq!(colony.best_tour_edge_stats(1.0), (3, 2));
+        assert_eq!(colony.best_tour_edge_stats(5.0), (3, 0));
+        assert_eq!(colony.best_tour_edge_stats(0.1), (3, 3));
+    }
+
+    /// Tests each bag's contribution accounts for the best refill of the capacity it frees
+    #[test]
+    fn best_tour_bag_contributions() {
+        let bags: Vec<Bag> = [(10.0, 30.0), (5.0, 20.0), (5.0, 5.0), (8.0, 12.0)]
+            .iter()
+            .enumerate()
+            .map(|(number, (weight, cost))| Bag { number: number as i64, weight: *weight, cost: *cost, ratio: cost / weight, h: 1.0 })
+            .collect();
+        let mut colony = Colony::from_graph_and_ants(Graph::from_bags(20.0, bags), Vec::new());
+        colony.global_best = Tour { bags: vec![0, 1, 2], cost: 55.0, weight: 20.0 };
+        // Only removing bag 0 frees enough capacity for bag 3
+        assert_eq!(
+            colony.best_tour_bag_contributions(),
+            vec![(0, 18.0, 37.0), (1, 20.0, 35.0), (2, 5.0, 50.0)]
+        );
+    }
+
+    /// Tests only the chosen best tour's edges get pheromone under the best tour sources
+    #[test]
+    fn deposit_source() {
+        let deposited = |source: DepositSource| {
+            let mut colony = finished_colony();
+            colony.graph.graph[1].cost = 20.0;
+            colony.ants[0].current_cost = 30.0;
+            colony.global_best = Tour { bags: vec![1, 2], cost: 100.0, weight: 20.0 };
+            colony.incumbent = colony.global_best.clone();
+            colony.update_edges(0.0, 1.0, &UpdateRule { source, ..Default::default() }).unwrap();
+            [(0, 1), (2, 3), (1, 2)].map(|(i, j)| colony.graph.tau.get_edge(i, j) > 0.0)
+        };
+        assert_eq!(deposited(DepositSource::AllAnts), [true, true, false]);
+        assert_eq!(deposited(DepositSource::IterationBest), [true, false, false]);
+        assert_eq!(deposited(DepositSource::GlobalBest), [false, false, true]);
+    }
+
+    /// Tests the best tour accessor returns the global best, even after a worse iteration
+    #[test]
+    fn best_tour() {
+        let mut colony = finished_colony();
+        colony.update_edges(0.0, 1.0, &UpdateRule::default()).unwrap();
+        let best = colony.best_tour().clone();
+        assert_eq!(best, colony.global_best);
+        assert_eq!((best.cost(), best.weight()), (colony.global_best.cost, colony.global_best.weight));
+        assert!(!best.is_empty());
+
+        // The same tour at a lower cost makes the next iteration worse
+        let mut worse = colony.ants[0].clone();
+        worse.current_cost -= 5.0;
+        colony.ants = vec![worse];
+        colony.update_edges(0.0, 1.0, &UpdateRule::default()).unwrap();
+        assert!(colony.best_path.cost < best.cost);
+        assert_eq!(colony.best_tour(), &best);
+    }
+
+    /// Tests an edge left without deposits decays faster than one reinforced every update
+    #[test]
+    fn edge_age_evaporation() {
+        let rule = UpdateRule { evaporation: EvaporationMode::EdgeAge { gain: 1.0 }, ..Default::default() };
+        let mut colony = finished_colony();
+        let mut uniform = finished_colony();
+        for (i, j) in [(0, 1), (1, 2)] {
+            colony.graph.tau.set_edge(i, j, 1.0);
+            uniform.graph.tau.set_edge(i, j, 1.0);
+        }
+        for _ in 0..3 {
+            colony.update_edges(0.1, 1.0, &rule).unwrap();
+            uniform.update_edges(0.1, 1.0, &UpdateRule::default()).unwrap();
+        }
+        // Edge (0, 1) gets a deposit every update, edge (1, 2) never does
+        assert_eq!(colony.edge_age.get_edge(0, 1), 0.0);
+        assert_eq!(colony.edge_age.get_edge(1, 2), 3.0);
+        assert!((colony.graph.tau.get_edge(1, 2) - 0.8 * 0.7 * 0.6).abs() < 1e-12);
+        assert!((uniform.graph.tau.get_edge(1, 2) - 0.9_f64.powi(3)).abs() < 1e-12);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), uniform.graph.tau.get_edge(0, 1));
+    }
+
+    /// Tests a pushed ant deposits exactly its tour's cost * p_rate / weight on its edge
+    #[test]
+    fn push_ant() {
+        let mut colony = Colony::from_graph_and_ants(small_graph(), Vec::new());
+        colony.graph.graph[3].cost = 25.0;
+        let mut ant = Ant::birth(1, &colony.graph);
+        ant.tour.push(3);
+        ant.current_bag = 3;
+        ant.current_cost = 35.0;
+        ant.current_weight = 20.0;
+        colony.push_ant(ant);
+        assert_eq!(colony.ants.len(), 1);
+
+        colony.update_edges(0.0, 2.0, &UpdateRule::default()).unwrap();
+        assert_eq!(colony.graph.tau.get_edge(1, 3), 35.0 * 2.0 / 20.0);
+        assert_eq!(colony.graph.tau.get_edge(0, 1), 0.0);
+    }
+
+    /// Tests the best next bag is the one with the highest pheromone and heuristic weight
+    #[test]
+    fn best_next_bag() {
+        let mut graph = small_graph();
+        graph.graph[3].h = 2.0;
+        graph.tau.set_edge(0, 1, 1.0);
+        graph.tau.set_edge(0, 2, 3.0);
+        graph.tau.set_edge(0, 3, 1.0);
+        let ant = Ant::birth(0, &graph);
+        assert_eq!(ant.best_next_bag(&graph, 1.0), Some(2));
+        // Alpha of 0 leaves only the heuristic
+        assert_eq!(ant.best_next_bag(&graph, 0.0), Some(3));
+
+        let mut full = ant.clone();
+        full.tour.push(1);
+        full.current_bag = 1;
+        full.current_weight = 20.0;
+        assert_eq!(full.best_next_bag(&graph, 1.0), None);
+        assert_eq!(ant.tour, vec![0]);
+    }
+
+    /// Tests the branching factor averages the bags to choose from over every step that adds a bag
+    #[test]
+    fn average_branching_factor() {
+        // Three bags fit, so each ant chooses from 3 then 2 bags
+        let graph: Vec<Bag> = (0..4)
+            .map(|number| Bag { number, weight: 10.0, cost: 10.0, ratio: 1.0, h: 1.0 })
+            .collect();
+        let graph = Graph::from_bags(30.0, graph);
+        let ants = vec![Ant::birth(0, &graph), Ant::birth(1, &graph)];
+        let mut colony = Colony::from_graph_and_ants(graph, ants);
+        assert_eq!(colony.average_branching_factor(), 0.0);
+        colony.run_tours(1.0, None);
+        assert_eq!(colony.average_branching_factor(), 2.5);
+
+        // A new iteration starts counting again
+        colony.init_ants(1, StartStrategy::Random);
+        assert_eq!(colony.average_branching_factor(), 0.0);
+    }
+
+    /// Tests the improvement rate is steep while the global best climbs and drops to 0 once it flattens
+    #[test]
+    fn improvement_rate() {
+        let global_bests = [100.0, 200.0, 300.0, 400.0, 450.0, 460.0, 460.0, 460.0, 460.0, 460.0];
+        let history: Vec<IterationResult> = global_bests
+            .iter()
+            .enumerate()
+            .map(|(i, global_best)| IterationResult {
+                iteration: i + 1,
+                evaluations: (i as i64 + 1) * 10,
+                iteration_best: *global_best,
+                global_best: *global_best,
+                average: 0.0,
+                diversity: None,
+                entropy: None,
+            })
+            .collect();
+        assert_eq!(super::improvement_rate(&history[..4], 3), Some(100.0));
+        assert_eq!(super::improvement_rate(&history[..6], 4), Some(65.0));
+        assert_eq!(super::improvement_rate(&history, 4), Some(0.0));
+        // The window is cut to the history there is
+        assert_eq!(super::improvement_rate(&history[..3], 10), Some(100.0));
+        assert_eq!(super::improvement_rate(&history[..1], 3), None);
+        assert_eq!(super::improvement_rate(&history, 0), None);
+    }
+
+    /// Tests an ant's edges are the consecutive pairs of its tour
+    #[test]
+    fn ant_edges() {
+        let graph = small_graph();
+        let mut ant = Ant::birth(2, &graph);
+        assert_eq!(ant.edges().count(), 0);
+        ant.tour.extend([0, 3, 1]);
+        assert_eq!(ant.edges().collect::<Vec<(usize, usize)>>(), vec![(2, 0), (0, 3), (3, 1)]);
+        assert!(Tour::from(&ant).edges().eq(ant.edges()));
+    }
+
+    /// Tests an acceptance policy can make a worse tour the incumbent early in the run only, keeping the true global best
+    #[test]
+    fn acceptance_policy() {
+        fn early_anything(incumbent: f64, candidate: f64, iteration_fraction: f64) -> bool {
+            iteration_fraction < 0.1 || candidate > incumbent
+        }
+        let tour = |bags: Vec<usize>, cost: f64| Tour { bags, cost, weight: 10.0 };
+        let mut colony = Colony::new(small_graph());
+        colony.acceptance = Some(early_anything);
+        colony.fitness_budget = Some(100);
+        colony.record_iteration(tour(vec![0, 1], 40.0), 4, 30.0);
+        colony.record_iteration(tour(vec![2], 20.0), 4, 20.0);
+        // The worse tour is accepted within the first 10% of the budget
+        assert_eq!(colony.incumbent.cost, 20.0);
+        assert_eq!(colony.global_best.cost, 40.0);
+        assert_eq!(colony.stagnation, 1);
+        assert_eq!(colony.last_improvement, 4);
+        colony.record_iteration(tour(vec![0, 1], 30.0), 4, 30.0);
+        colony.record_iteration(tour(vec![3], 10.0), 4, 10.0);
+        assert_eq!(colony.incumbent.cost, 30.0);
+        // Improvements are measured against the true best, which no later tour beat
+        assert_eq!(colony.global_best.cost, 40.0);
+        assert_eq!(colony.stagnation, 3);
+        assert_eq!(colony.last_improvement, 4);
+        assert_eq!(colony.history.last().unwrap().global_best, 40.0);
+
+        // Without a policy only strict improvements are accepted
+        let mut colony = Colony::new(small_graph());
+        colony.record_iteration(tour(vec![0, 1], 40.0), 4, 30.0);
+        colony.record_iteration(tour(vec![2], 20.0), 4, 20.0);
+        assert_eq!(colony.global_best.cost, 40.0);
+        assert_eq!(colony.incumbent, colony.global_best);
+    }
+
+    /// Tests local search probes count towards the fitness evaluations only when asked to
+    #[test]
+    fn local_search_evaluations() {
+        let record = |count_local_search: bool| {
+            let mut colony = Colony::new(small_graph());
+            colony.graph.graph[3].cost = 30.0;
+            colony.local_search = true;
+            colony.count_local_search = count_local_search;
+            colony.record_iteration(Tour { bags: vec![0, 1], cost: 20.0, weight: 20.0 }, 2, 20.0);
+            colony
+        };
+        // Bag 0 is swapped for bag 3 on the second probe, then no swap of
+        // either bag for the two left out improves, which takes 4 probes
+        let counted = record(true);
+        assert_eq!(counted.best_path, Tour { bags: vec![3, 1], cost: 40.0, weight: 20.0 });
+        assert_eq!(counted.num_of_fitness_evaluations, 2 + 6);
+        assert_eq!(counted.history[0].evaluations, 8);
+        let uncounted = record(false);
+        assert_eq!(uncounted.global_best, counted.global_best);
+        assert_eq!(uncounted.num_of_fitness_evaluations, 2);
+
+        // A tour no swap improves is left as it is
+        let colony = Colony::new(small_graph());
+        let tour = Tour { bags: vec![0, 1], cost: 20.0, weight: 20.0 };
+        assert_eq!(colony.swap_search(tour.clone()), (tour, 4));
+    }
+}
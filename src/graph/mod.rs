@@ -1,14 +1,14 @@
-use std::convert::TryInto;
-use std::path::Path;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use std::fs;
 use core::fmt;
+use std::cmp::Ordering;
 use rand::Rng;
 
-/// Constant size of the number of bags in the text file
-/// !!! Important !!!
-/// Modify this carfully, depending on the BankProblem files
-/// you use
-const BAG_NUMBER: usize = 100;
+/// Pheromone values that overflow are clamped to this, low enough that
+/// weighting an edge by alpha up to 3 in the selection rule stays finite
+pub const MAX_PHEROMONE: f64 = 1e100;
 
 /// Represents each bag and its meta data
 /// number: Bag number
@@ -32,27 +32,243 @@ impl fmt::Display for Bag {
     }
 }
 
+impl Bag {
+    /// Orders bags by their cost/weight ratio, highest first.
+    /// Bags with the same ratio are ordered by weight, lightest
+    /// first, then by bag number so the ordering is always stable.
+    /// Use this wherever bags are ranked by ratio
+    pub fn cmp_by_ratio(&self, other: &Self) -> Ordering {
+        other.ratio
+            .partial_cmp(&self.ratio)
+            .unwrap_or(Ordering::Equal)
+            .then(self.weight.partial_cmp(&other.weight).unwrap_or(Ordering::Equal))
+            .then(self.number.cmp(&other.number))
+    }
+}
+
 impl PartialOrd for Bag {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         self.number.partial_cmp(&other.number)
     }
 }
 
+/// Errors raised when a problem cannot be loaded into a graph
+#[derive(Debug, PartialEq)]
+pub enum GraphError {
+    /// A bag has a negative value, which only happens with a malformed problem file
+    NegativeCost { bag: i64, cost: f64 },
+    /// A line of the problem file could not be read
+    Parse(String),
+    /// A file could not be read or written
+    Io(String),
+    /// A tour visits the same bag more than once, which would count its value twice
+    DuplicateBag { bag: i64 },
+    /// A pheromone value overflowed to inf or NaN during an update
+    PheromoneOverflow { bag_i: i64, bag_j: i64 },
+    /// The problem has no bags that can be selected, so there is nothing for ants to visit
+    EmptyProblem,
+    /// A van's capacity is zero or negative, so no bag could ever be added
+    InvalidCapacity { capacity: f64 },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::NegativeCost { bag, cost } => {
+                write!(f, "Bag {} has a negative value of {}, check the problem file", bag, cost)
+            }
+            GraphError::Parse(message) => write!(f, "Unable to parse problem file: {}", message),
+            GraphError::Io(message) => write!(f, "Unable to access file: {}", message),
+            GraphError::DuplicateBag { bag } => write!(f, "Bag {} appears more than once in the tour", bag),
+            GraphError::PheromoneOverflow { bag_i, bag_j } => write!(
+                f,
+                "Pheromone between bags {} and {} is no longer finite, lower p_rate or bound the pheromones as in MMAS",
+                bag_i, bag_j
+            ),
+            GraphError::EmptyProblem => write!(f, "The problem has no bags with a value, check the problem file"),
+            GraphError::InvalidCapacity { capacity } => {
+                write!(f, "Van capacity of {} is not positive, check the problem file", capacity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
 /// Represents the graph used to store bags and meta data.
 /// Vectors are used over arrays to avoid stack overflow errors
 /// with large data sets. Since vectors are only accessed, 
 /// capacity change is never needed after creation so 
 /// the performance loss is minimal and acceptable.
-/// max_weight: The max weight constraint of the problem
 /// nodes: the number of nodes in the problem
 /// graph: Constant size collection of Bags with a fixed indicies
 /// tau: Tau struct containing pheromone data
-#[derive(Debug)]
+/// capacities: Capacity of every van, a single van for the knapsack
+///     problem and several for multi-knapsack problems, see max_weight
+/// integer_weights: True if every bag weight and capacity is a whole number,
+///     capacity checks then compare integers so float drift in a running
+///     weight total cannot reject a bag that exactly fills a van
+/// candidate_list: If set, ants only choose between the given number of
+///     highest ratio bags they can still add
+/// ratio_order: Indices of all bags ranked by ratio, computed once when the
+///     graph is built so candidate lists are gathered without sorting
+/// selection_rule: If set, the weight of an edge in the selection wheel is
+///     selection_rule(tau, h) instead of tau^alpha * h, where tau is the
+///     edge's pheromone and h the next bag's heuristic. See SelectionRule
+/// disable_heuristic: If true, every bag's h is taken as 1 when selecting,
+///     so selection only follows the pheromones. Unlike beta of 0 this
+///     holds whatever h was computed from, e.g. a blended heuristic
+/// heuristic_cache: Every bag's h for each beta asked for, keyed by the
+///     bits of beta and indexed as the graph, see heuristic_for_beta
+/// skipped_bags: Numbers of the bags in the problem file that had no value,
+///     so were dropped when loading
+#[derive(Debug, Clone)]
 pub struct Graph {
-    pub max_weight: f64,
     pub nodes: usize,
     pub graph: Vec<Bag>,
     pub tau: Tau,
+    pub capacities: Vec<f64>,
+    pub integer_weights: bool,
+    pub candidate_list: Option<usize>,
+    pub ratio_order: Vec<usize>,
+    pub selection_rule: Option<SelectionRule>,
+    pub disable_heuristic: bool,
+    heuristic_cache: HashMap<u64, Vec<f64>>,
+    pub skipped_bags: Vec<i64>,
+}
+
+/// A measure of how desirable a bag is on its own, blended into a
+/// bag's h by HeuristicTerm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Heuristic {
+    /// The bag's cost/weight ratio
+    Ratio,
+    /// One over the bag's weight, favouring light bags whatever their value
+    InverseWeight,
+}
+
+impl Heuristic {
+    /// Returns the heuristic's value for the given bag
+    pub fn value(&self, bag: &Bag) -> f64 {
+        match self {
+            Heuristic::Ratio => bag.ratio,
+            Heuristic::InverseWeight => 1.0 / bag.weight,
+        }
+    }
+}
+
+/// One weighted component of a blended heuristic, contributing
+/// weight * heuristic^exponent to a bag's h. See Graph::set_heuristic
+/// heuristic: The component heuristic
+/// weight: Scalar weight of the component in the blend
+/// exponent: Power the component is raised to, as beta for ratio^beta
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeuristicTerm {
+    pub heuristic: Heuristic,
+    pub weight: f64,
+    pub exponent: f64,
+}
+
+/// A custom weighting of an edge in the selection wheel, given the
+/// edge's pheromone and the next bag's heuristic. Weights are
+/// normalized over the availible bags, so they need not sum to 1
+pub type SelectionRule = fn(tau: f64, h: f64) -> f64;
+
+/// Summary of the pheromones on every edge between the graph's bags,
+/// see Tau::stats
+///     min: Smallest pheromone value of any edge
+///     max: Largest pheromone value of any edge
+///     mean: Mean pheromone value over all edges
+///     nonzero: Number of edges with any pheromone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PheromoneStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub nonzero: usize,
+}
+
+impl fmt::Display for PheromoneStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Pheromones min/max/mean: {:.4}/{:.4}/{:.4} | Nonzero edges: {}",
+            self.min, self.max, self.mean, self.nonzero
+        )
+    }
+}
+
+/// How much pheromone a tour deposits on each of its edges
+///     ValuePerWeight: The tour's cost divided by its weight, see Graph::deposit_phero
+///     CapacityWeighted: The tour's cost scaled by its weight over the
+///         capacity of every van, so of two tours of equal cost the one
+///         that fills the vans more reinforces more strongly
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DepositRule {
+    #[default]
+    ValuePerWeight,
+    CapacityWeighted,
+}
+
+/// How strictly a problem file is read
+///     Strict: Every line must be a capacity, bag, weight or value line,
+///         with the capacity first and each bag followed by its weight
+///         and value. Anything else, including comments, is a
+///         GraphError::Parse. Blank lines are allowed
+///     Lenient: Comments starting with '#', blank lines and unrecognised
+///         lines are skipped, and the capacity may be on any line
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ParseMode {
+    Strict,
+    #[default]
+    Lenient,
+}
+
+/// How pheromone matrices from several colonies are combined
+///     Average: Each edge becomes the mean of the edge across all matrices
+///     Max: Each edge becomes the largest value of the edge across all matrices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeStrategy {
+    Average,
+    Max,
+}
+
+/// Summary of a loaded problem, used to sanity check the
+/// right problem file was loaded
+///     bags: Number of bags in the problem
+///     capacity: The max weight constraint of the problem
+///     total_weight: Weight of all bags combined
+///     total_cost: Value of all bags combined
+///     min_ratio, max_ratio, mean_ratio: Spread of the bags' cost/weight ratios
+///     expected_tour_length: Rough number of bags in a feasible tour, see
+///         Graph::expected_tour_length
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemSummary {
+    pub bags: usize,
+    pub capacity: f64,
+    pub total_weight: f64,
+    pub total_cost: f64,
+    pub min_ratio: f64,
+    pub max_ratio: f64,
+    pub mean_ratio: f64,
+    pub expected_tour_length: f64,
+}
+
+impl fmt::Display for ProblemSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Bags: {}\nCapacity: {}\nTotal Weight: {}\nTotal Cost: {}\nRatio min/max/mean: {}/{}/{}\nExpected Tour Length: {:.1}",
+            self.bags,
+            self.capacity,
+            self.total_weight,
+            self.total_cost,
+            self.min_ratio,
+            self.max_ratio,
+            self.mean_ratio,
+            self.expected_tour_length,
+        )
+    }
 }
 
 /// Contains the pheromones values on edges. Stores information
@@ -61,19 +277,46 @@ pub struct Graph {
 /// where i < j is always true for any edge get/set operations
 /// 
 /// See modules tests for validation
-#[derive(Debug)]
+/// nodes: Number of bags the matrix has rows for
+#[derive(Debug, Clone)]
 pub struct Tau {
-    matrix: Vec<Vec<f64>>
+    matrix: Vec<Vec<f64>>,
+    nodes: usize,
 }
 
 impl Tau {
     /// Creates a new matrix to store pheromone values in
-    pub fn new() -> Self {
-        Tau {matrix: vec![vec![0.0; BAG_NUMBER]; BAG_NUMBER]}
+    /// nodes: Number of bags in the problem, sizing the matrix
+    pub fn new(nodes: usize) -> Self {
+        Tau {matrix: vec![vec![0.0; nodes]; nodes], nodes}
     }
     
+    /// Returns the number of bags the matrix has rows for
+    pub fn size(&self) -> usize {
+        self.nodes
+    }
+
+    /// Returns the min, max, mean and number of nonzero pheromones
+    /// over every edge of the matrix
+    pub fn stats(&self) -> PheromoneStats {
+        let nodes = self.nodes;
+        let values: Vec<f64> = (0..nodes)
+            .flat_map(|i| (i+1..nodes).map(move |j| (i, j)))
+            .map(|(i, j)| self.get_edge(i, j))
+            .collect();
+        if values.is_empty() {
+            return PheromoneStats { min: 0.0, max: 0.0, mean: 0.0, nonzero: 0 };
+        }
+        PheromoneStats {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            nonzero: values.iter().filter(|value| **value != 0.0).count(),
+        }
+    }
+
     /// Returns the raw metrix, use with caution
-    pub fn get_matrix(&mut self) -> &Vec<Vec<f64>>{//[[f64; BAG_NUMBER]; BAG_NUMBER] {
+    pub fn get_matrix(&mut self) -> &Vec<Vec<f64>>{
         &self.matrix
     }
     
@@ -95,6 +338,14 @@ impl Tau {
         }
     }
 
+    /// Returns the full symmetric pheromone matrix, filled in from the
+    /// i < j storage, for numerical analysis such as eigenvalues or
+    /// clustering of the learned edges
+    #[cfg(feature = "ndarray")]
+    pub fn to_ndarray(&self) -> ndarray::Array2<f64> {
+        ndarray::Array2::from_shape_fn((self.nodes, self.nodes), |(i, j)| self.get_edge(i, j))
+    }
+
     /// Adds the given values to the given edge
     pub fn add_to_edge(&mut self, bag_i: usize, bag_j: usize, value: f64) {
         if bag_i < bag_j {
@@ -103,6 +354,86 @@ impl Tau {
             self.matrix[bag_j][bag_i] += value;
         }
     }
+
+    /// Returns the first edge whose pheromone is inf or NaN, None if
+    /// every edge is finite
+    pub fn first_non_finite(&self) -> Option<(usize, usize)> {
+        let nodes = self.nodes;
+        (0..nodes)
+            .flat_map(|i| (i+1..nodes).map(move |j| (i, j)))
+            .find(|(i, j)| !self.get_edge(*i, *j).is_finite())
+    }
+
+    /// Replaces every inf or NaN pheromone with limit
+    /// Returns the number of edges clamped
+    pub fn clamp_non_finite(&mut self, limit: f64) -> usize {
+        let mut clamped = 0;
+        for i in 0..self.nodes {
+            for j in i+1..self.nodes {
+                if !self.get_edge(i, j).is_finite() {
+                    self.set_edge(i, j, limit);
+                    clamped += 1;
+                }
+            }
+        }
+        clamped
+    }
+
+    /// Writes the matrix to a file, one row per line with the values
+    /// separated by commas, so a later run can start from it
+    pub fn save(&self, path: &Path) -> Result<(), GraphError> {
+        let rows: Vec<String> = self.matrix
+            .iter()
+            .map(|row| row.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(","))
+            .collect();
+        fs::write(path, rows.join("\n") + "\n")
+            .map_err(|error| GraphError::Io(format!("{}: {}", path.display(), error)))
+    }
+
+    /// Reads a matrix written by save
+    /// Returns a GraphError if the file cannot be read or is not a square matrix
+    pub fn load(path: &Path) -> Result<Tau, GraphError> {
+        let data = fs::read_to_string(path)
+            .map_err(|error| GraphError::Io(format!("{}: {}", path.display(), error)))?;
+        let matrix: Vec<Vec<f64>> = data
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split(',')
+                    .map(|value| value.trim().parse::<f64>()
+                        .map_err(|_| GraphError::Parse(format!("invalid pheromone value \"{}\"", value))))
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        if matrix.iter().any(|row| row.len() != matrix.len()) {
+            return Err(GraphError::Parse(format!("pheromone matrix in {} is not square", path.display())));
+        }
+        Ok(Tau { nodes: matrix.len(), matrix })
+    }
+
+    /// Merges the pheromones of other colonies into this matrix, edge
+    /// by edge, according to the given strategy. Used to periodically
+    /// sync pheromones between colonies run in parallel
+    /// Panics if another matrix is not of the same size, as its edges
+    /// would be of other bags
+    pub fn merge(&mut self, others: &[&Tau], strategy: MergeStrategy) {
+        for other in others {
+            assert!(
+                other.size() == self.size(),
+                "cannot merge a pheromone matrix of {} bags into one of {} bags", other.size(), self.size()
+            );
+        }
+        let count = (others.len() + 1) as f64;
+        for i in 0..self.matrix.len() {
+            for j in 0..self.matrix[i].len() {
+                let values = others.iter().map(|other| other.matrix[i][j]);
+                self.matrix[i][j] = match strategy {
+                    MergeStrategy::Average => (self.matrix[i][j] + values.sum::<f64>()) / count,
+                    MergeStrategy::Max => values.fold(self.matrix[i][j], f64::max),
+                };
+            }
+        }
+    }
 }
 
 impl Graph {
@@ -112,23 +443,207 @@ impl Graph {
     /// are created, for performance gains, as thisv value
     /// is constant throughout the algorithm
     /// beta: weight for herisitc bias
-    pub fn construct_graph(beta: f64) -> Self {
-        let (max_weight, bags) = load_data(beta);
+    /// path: Path of the problem file, see problem_file for the default
+    /// Returns a GraphError if the problem file is malformed
+    pub fn construct_graph(beta: f64, path: &Path) -> Result<Self, GraphError> {
+        let (capacities, bags, skipped_bags) = load_data(beta, path)?;
+        Graph::from_loaded(capacities, bags, skipped_bags)
+    }
+
+    /// Constructs a new graph from the problem file at the given path,
+    /// parsed leniently, see construct_graph
+    /// Returns a GraphError if the file cannot be read or is malformed
+    pub fn from_file(path: &Path, beta: f64) -> Result<Self, GraphError> {
+        Graph::from_file_with_mode(path, beta, ParseMode::Lenient)
+    }
+
+    /// Constructs a new graph from the problem file at the given path,
+    /// see construct_graph
+    /// mode: How strictly the file is parsed, see ParseMode
+    /// Returns a GraphError if the file cannot be read or is malformed
+    pub fn from_file_with_mode(path: &Path, beta: f64, mode: ParseMode) -> Result<Self, GraphError> {
+        let (capacities, bags, skipped_bags) = load_file(path, beta, mode)?;
+        Graph::from_loaded(capacities, bags, skipped_bags)
+    }
+
+    /// Constructs a graph from a loaded problem file's capacities, bags
+    /// and the numbers of the bags skipped as they had no value
+    fn from_loaded(capacities: Vec<f64>, bags: Vec<Bag>, skipped_bags: Vec<i64>) -> Result<Self, GraphError> {
+        let mut graph = Graph::from_bags(capacities[0], bags);
+        graph.integer_weights &= capacities.iter().all(|capacity| is_integer(*capacity));
+        graph.capacities = capacities;
+        graph.skipped_bags = skipped_bags;
+        Ok(graph)
+    }
+
+    /// Constructs a graph with a single van from already loaded bags,
+    /// with no pheromones on any edge
+    pub fn from_bags(max_weight: f64, bags: Vec<Bag>) -> Self {
         let nodes = bags.len();
-        let graph: Vec<Bag> = bags.try_into().unwrap();        
-        let tau = Tau::new();
-        Graph {
-            max_weight,
+        let tau = Tau::new(nodes);
+        let integer_weights = is_integer(max_weight) && bags.iter().all(|bag| is_integer(bag.weight));
+        let mut graph = Graph {
             nodes,
-            graph,
+            graph: bags,
             tau,
+            capacities: vec![max_weight],
+            integer_weights,
+            candidate_list: None,
+            ratio_order: Vec::new(),
+            selection_rule: None,
+            disable_heuristic: false,
+            heuristic_cache: HashMap::new(),
+            skipped_bags: Vec::new(),
+        };
+        graph.ratio_order = graph.indices_by_ratio();
+        graph
+    }
+
+    /// Returns the capacity of the first van, the max weight constraint
+    /// of a single van problem
+    pub fn max_weight(&self) -> f64 {
+        self.capacities[0]
+    }
+
+    /// Returns the capacity of every van together
+    pub fn total_capacity(&self) -> f64 {
+        self.capacities.iter().sum()
+    }
+
+    /// Returns the index in the graph of the bag with the given
+    /// number from the problem file, or None if there is no such bag.
+    /// Numbers and indices differ once bags are dropped when loading
+    pub fn index_of(&self, bag_number: i64) -> Option<usize> {
+        self.graph.iter().position(|bag| bag.number == bag_number)
+    }
+
+    /// Returns the pheromone on the edge between the bags with the given
+    /// numbers from the problem file, or None if either number is unknown
+    pub fn pheromone_between(&self, bag_a: i64, bag_b: i64) -> Option<f64> {
+        Some(self.tau.get_edge(self.index_of(bag_a)?, self.index_of(bag_b)?))
+    }
+
+    /// Returns the problem file's number of the bag at the given index
+    pub fn number_at(&self, index: usize) -> i64 {
+        self.graph[index].number
+    }
+
+    /// Exports the graph as a Graphviz DOT undirected graph, drawing
+    /// only the edges with more pheromone than the threshold. Nodes are
+    /// labelled by bag number and edge thickness is proportional to the
+    /// edge's pheromone, scaled so the strongest edge has a width of 5
+    pub fn to_dot(&self, threshold: f64) -> String {
+        let mut edges: Vec<(usize, usize, f64)> = Vec::new();
+        for i in 0..self.graph.len() {
+            for j in i+1..self.graph.len() {
+                let value = self.tau.get_edge(i, j);
+                if value > threshold {
+                    edges.push((i, j, value));
+                }
+            }
+        }
+        let strongest = edges.iter().map(|edge| edge.2).fold(0.0, f64::max);
+
+        let mut dot = String::from("graph aco {\n");
+        for (i, j, value) in edges {
+            dot.push_str(&format!(
+                "    {} -- {} [penwidth={:.3}];\n",
+                self.number_at(i),
+                self.number_at(j),
+                5.0 * value / strongest,
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Summarises the loaded problem, see ProblemSummary
+    pub fn summary(&self) -> ProblemSummary {
+        let ratios = self.graph.iter().map(|bag| bag.ratio);
+        ProblemSummary {
+            bags: self.graph.len(),
+            capacity: self.max_weight(),
+            total_weight: self.graph.iter().map(|bag| bag.weight).sum(),
+            total_cost: self.graph.iter().map(|bag| bag.cost).sum(),
+            min_ratio: ratios.clone().fold(f64::INFINITY, f64::min),
+            max_ratio: ratios.clone().fold(f64::NEG_INFINITY, f64::max),
+            mean_ratio: ratios.sum::<f64>() / self.graph.len() as f64,
+            expected_tour_length: self.expected_tour_length(),
+        }
+    }
+
+    /// Estimates how many bags a typical feasible tour holds, as the
+    /// capacity over the mean bag weight, capped at the number of bags.
+    /// A guide for sizing candidate lists, see Graph::candidate_list
+    pub fn expected_tour_length(&self) -> f64 {
+        if self.graph.is_empty() {
+            return 0.0;
+        }
+        let mean_weight = self.graph.iter().map(|bag| bag.weight).sum::<f64>() / self.graph.len() as f64;
+        (self.max_weight() / mean_weight).min(self.graph.len() as f64)
+    }
+
+    /// Returns the indices of all bags, ranked by ratio according
+    /// to Bag::cmp_by_ratio
+    pub fn indices_by_ratio(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..self.graph.len()).collect();
+        indices.sort_by(|a, b| self.graph[*a].cmp_by_ratio(&self.graph[*b]));
+        indices
+    }
+
+    /// Reorders the bags in the graph by ratio, according to
+    /// Bag::cmp_by_ratio, carrying the pheromones of each edge along.
+    /// High ratio bags are favoured by the selection rule, so keeping
+    /// them together at the front is friendlier to the cache in the
+    /// selection loops. Bag numbers are unchanged, so index_of and
+    /// number_at must be used to map between numbers and indices
+    pub fn sort_by_ratio(&mut self) {
+        let order: Vec<usize> = self.indices_by_ratio();
+        let mut tau = Tau::new(self.nodes);
+        for i in 0..order.len() {
+            for j in i+1..order.len() {
+                tau.set_edge(i, j, self.tau.get_edge(order[i], order[j]));
+            }
+        }
+        self.graph = order.iter().map(|index| self.graph[*index]).collect();
+        self.tau = tau;
+        self.ratio_order = (0..self.graph.len()).collect();
+        // Cached heuristics follow the old bag order
+        self.heuristic_cache.clear();
+    }
+
+    /// Returns every bag's heuristic, ratio^beta, for the given beta,
+    /// indexed as the graph. Each beta is only computed once, so sweeps
+    /// over beta need no reload of the problem file
+    pub fn heuristic_for_beta(&mut self, beta: f64) -> &[f64] {
+        let bags = &self.graph;
+        self.heuristic_cache
+            .entry(beta.to_bits())
+            .or_insert_with(|| bags.iter().map(|bag| bag.ratio.powf(beta)).collect())
+    }
+
+    /// Sets every bag's heuristic to the one of the given beta, see
+    /// heuristic_for_beta
+    pub fn set_beta(&mut self, beta: f64) {
+        let h = self.heuristic_for_beta(beta).to_vec();
+        self.graph.iter_mut().zip(h).for_each(|(bag, h)| bag.h = h);
+    }
+
+    /// Sets every bag's heuristic to a weighted blend of component
+    /// heuristics, h = w1 * h1^a + w2 * h2^b + ..., for example
+    /// h = w1 * ratio^a + w2 * (1/weight)^b. A single Ratio term of
+    /// weight 1 and exponent beta is the same as set_beta(beta)
+    /// terms: Components of the blend
+    pub fn set_heuristic(&mut self, terms: &[HeuristicTerm]) {
+        for bag in self.graph.iter_mut() {
+            bag.h = blended_heuristic(bag, terms);
         }
     }
 
     /// Distributes a uniform pheromone values across
     /// all edges
-    pub fn initialize_tau(&mut self) {
-        let mut rng = rand::thread_rng();
+    /// rng: Random number generator the values are drawn from
+    pub fn initialize_tau<R: Rng>(&mut self, rng: &mut R) {
         let bags = &self.graph;
 
         for i in 0..bags.len() {
@@ -141,6 +656,29 @@ impl Graph {
         }
     }
 
+    /// Returns the total cost and weight of a tour of bag indices
+    /// Returns a GraphError if the tour visits a bag more than once
+    pub fn evaluate_tour(&self, tour: &[usize]) -> Result<(f64, f64), GraphError> {
+        if let Some(bag) = first_duplicate(tour) {
+            return Err(GraphError::DuplicateBag { bag: self.number_at(bag) });
+        }
+        Ok((
+            tour.iter().map(|bag| self.graph[*bag].cost).sum(),
+            tour.iter().map(|bag| self.graph[*bag].weight).sum(),
+        ))
+    }
+
+    /// Returns true if the weight fits within the allowed weight. With
+    /// integer weights both are rounded to integers first, so an allowed
+    /// weight that drifted just below a whole number is still respected
+    pub fn within_weight(&self, weight: f64, allowed_weight: f64) -> bool {
+        if self.integer_weights {
+            (weight.round() as i64) <= (allowed_weight.round() as i64)
+        } else {
+            weight <= allowed_weight
+        }
+    }
+
     /// Gets all possible bags which can be visited next,
     /// according to the given arguments
     /// current_bag: The current bag_i to be checked
@@ -148,22 +686,101 @@ impl Graph {
     ///     unavaible for future traversal
     /// allowed_weight: The maximium weight of any future bag
     ///     according to constraints
+    /// Returns the bags in ascending index order whatever filtering is
+    /// applied, as the selection wheel is built in this order and a
+    /// seeded search must draw the same bags.
     /// Returns empty vector if no bags are possible.
     pub fn get_availible_bags(
         &self,
         current_bag: &usize,
-        visited_bags: &Vec<usize>,
+        visited_bags: &[usize],
         allowed_weight: f64,
     ) -> Vec<usize> {
-        self.graph
-            .iter().enumerate()
-            .filter(|&bag| {
-                bag.0 != *current_bag
-                && !visited_bags.contains(&&bag.0)
-                && bag.1.weight <= allowed_weight
-            })
-            .map(|bag| bag.0)
-            .collect()
+        let is_availible = |bag: &usize| {
+            *bag != *current_bag
+            && !visited_bags.contains(bag)
+            && self.within_weight(self.graph[*bag].weight, allowed_weight)
+        };
+        match self.candidate_list {
+            // The first availible bags in ratio order, at least one so
+            // a tour is never cut short while a bag still fits. Only
+            // these few are sorted, graph order is already ascending
+            Some(candidates) => {
+                let mut availible_bags: Vec<usize> = self.ratio_order
+                    .iter()
+                    .copied()
+                    .filter(is_availible)
+                    .take(candidates.max(1))
+                    .collect();
+                availible_bags.sort_unstable();
+                availible_bags
+            }
+            None => (0..self.graph.len()).filter(is_availible).collect(),
+        }
+    }
+
+    /// Builds one uniformly random tour that respects the weight
+    /// constraint, adding random bags until no other bag fits.
+    /// Useful as a baseline and in tests
+    /// rng: Random number generator to draw bags from
+    /// Returns (Tour as Vec<Bag references as usize>, cost, weight)
+    pub fn random_feasible_tour<R: Rng>(&self, rng: &mut R) -> (Vec<usize>, f64, f64) {
+        let mut tour: Vec<usize> = Vec::new();
+        let mut cost: f64 = 0.0;
+        let mut weight: f64 = 0.0;
+        loop {
+            // Before the first bag there is no current bag, so an index
+            // past the last bag is used which never matches a bag
+            let current_bag = tour.last().copied().unwrap_or(self.nodes);
+            let availible_bags = self.get_availible_bags(&current_bag, &tour, self.max_weight() - weight);
+            if availible_bags.is_empty() {
+                break;
+            }
+            let bag = availible_bags[rng.gen_range(0..availible_bags.len())];
+            tour.push(bag);
+            cost += self.graph[bag].cost;
+            weight += self.graph[bag].weight;
+        }
+        (tour, cost, weight)
+    }
+
+    /// Builds the greedy tour, taking bags from the highest ratio down
+    /// and skipping any bag that no longer fits. On many instances this
+    /// is already optimal or close to it, so it is the baseline ACO
+    /// has to beat
+    /// Returns (Tour as Vec<Bag references as usize>, cost, weight)
+    pub fn greedy_solution(&self) -> (Vec<usize>, f64, f64) {
+        let mut tour: Vec<usize> = Vec::new();
+        let mut cost: f64 = 0.0;
+        let mut weight: f64 = 0.0;
+        for bag in self.indices_by_ratio() {
+            if self.within_weight(self.graph[bag].weight, self.max_weight() - weight) {
+                tour.push(bag);
+                cost += self.graph[bag].cost;
+                weight += self.graph[bag].weight;
+            }
+        }
+        (tour, cost, weight)
+    }
+
+    /// Returns the optimum of the LP relaxation, the fractional knapsack
+    /// where bags are taken from the highest ratio down and the first
+    /// bag that no longer fits is taken in part. No tour can beat it, so
+    /// it bounds the optimum from above
+    pub fn lp_upper_bound(&self) -> f64 {
+        let mut capacity = self.max_weight();
+        let mut cost: f64 = 0.0;
+        for bag in self.indices_by_ratio() {
+            let bag = &self.graph[bag];
+            if bag.weight <= capacity {
+                cost += bag.cost;
+                capacity -= bag.weight;
+            } else {
+                cost += bag.cost * capacity / bag.weight;
+                break;
+            }
+        }
+        cost
     }
 
     /// Uses fitness proportional selection (roulette wheel) to
@@ -171,14 +788,16 @@ impl Graph {
     /// bag_i: The current bag
     /// availible_bags: All bags that can be visited next
     /// alpha: Scalar weight for edge's pheromones
+    /// rng: Random number generator used to spin the wheel
     /// Returns Some(index to bag in graph)
     /// 
     /// See modules tests for validation
-    pub fn select_path(
+    pub fn select_path<R: Rng>(
         &self,
         bag_i: &usize,
-        availible_bags: &Vec<usize>,
+        availible_bags: &[usize],
         alpha: f64,
+        rng: &mut R,
     ) -> Option<usize> {
         // If there is only one bag left, then just
         // return that one for faster performance
@@ -188,16 +807,29 @@ impl Graph {
             // Gets the wheel with calculated, ranked probabilities
             let wheel: Vec<f64> = self.create_selection_wheel(bag_i, availible_bags, alpha);
             // Gets a random choice. Range is upto 1 since all ranks sum up to 1
-            let choice: f64 = rand::thread_rng().gen_range(0.0..=1.0);
+            let choice: f64 = rng.gen_range(0.0..=1.0);
             // Returns the correct bag given the wheel and random choice
-            availible_bags
-                .iter()
-                .zip(wheel.iter())
-                .find(|(_, &rank)| choice <= rank)
-                .map(|(bag, _)| *bag)
+            Graph::spin_wheel(availible_bags, &wheel, choice)
         }
     }
 
+    /// Returns the first bag whose cumulative probability on the wheel
+    /// meets the choice. Bags with no probability share the cumulative
+    /// value of the bag before them, so they are skipped and can never be
+    /// chosen, whichever side of them the choice lands
+    /// availible_bags: Bags laid out on the wheel
+    /// wheel: Cumulative probability of each bag
+    /// choice: Random value the wheel is spun to
+    fn spin_wheel(availible_bags: &[usize], wheel: &[f64], choice: f64) -> Option<usize> {
+        let previous = std::iter::once(0.0).chain(wheel.iter().copied());
+        availible_bags
+            .iter()
+            .zip(wheel.iter().zip(previous))
+            .filter(|(_, (&rank, previous))| rank > *previous)
+            .find(|(_, (&rank, _))| choice <= rank)
+            .map(|(bag, _)| *bag)
+    }
+
     /// Creates a routllet wheel given
     /// bag_i: The current bag
     /// availible_bags: All bags that can be visited next
@@ -206,7 +838,7 @@ impl Graph {
     fn create_selection_wheel(
         &self,
         bag_i: &usize,
-        availible_bags: &Vec<usize>,
+        availible_bags: &[usize],
         alpha: f64,
     ) -> Vec<f64> {        
         // Collect probabilities
@@ -225,6 +857,28 @@ impl Graph {
             .collect()
     }
 
+    /// Returns the probability of an ant at bag_i choosing bag_j out
+    /// of the availible bags, as used by the roulette wheel.
+    /// Exposed for inspecting and debugging selection behaviour
+    pub fn edge_probability(&self, bag_i: usize, bag_j: usize, availible_bags: &[usize], alpha: f64) -> f64 {
+        self.calculate_edge_probability(&bag_i, &bag_j, availible_bags, alpha)
+    }
+
+    /// Returns the roulette wheel an ant at bag_i spins to choose its
+    /// next bag, for diagnosing why a choice was made
+    /// availible_bags: All bags that can be visited next
+    /// alpha: Scalar weight for edge's pheromones
+    /// Returns (bag, probability, cumulative probability) for every
+    /// availible bag, in the order the wheel is laid out
+    pub fn debug_wheel(&self, bag_i: usize, availible_bags: &[usize], alpha: f64) -> Vec<(usize, f64, f64)> {
+        let wheel = self.create_selection_wheel(&bag_i, availible_bags, alpha);
+        availible_bags
+            .iter()
+            .zip(wheel)
+            .map(|(bag, cumulative)| (*bag, self.calculate_edge_probability(&bag_i, bag, availible_bags, alpha), cumulative))
+            .collect()
+    }
+
     /// Calculates the porbability of each edge, 
     /// according to the selection rules, given
     /// bag_i: The current bag index
@@ -236,7 +890,7 @@ impl Graph {
         &self,
         bag_i: &usize,
         bag_j: &usize,
-        availible_bags: &Vec<usize>,
+        availible_bags: &[usize],
         alpha: f64,
     ) -> f64 {
         // Update Rule
@@ -251,26 +905,40 @@ impl Graph {
         // 
         // otherwise
         // 0
-        let t: f64 = self.tau.get_edge(*bag_i, *bag_j).powf(alpha);
-        let h: f64 = self.graph[*bag_j].h;
-        
-        let sum_of_availible_bags: f64 = availible_bags
-            .iter()
-            .map(|bag| {
-                let t = self.tau.get_edge(*bag_i, *bag).powf(alpha);
-                t * self.graph[*bag].h
-            })
-            .sum::<f64>();
-        // Compute the edge probability
-        (t * h) / sum_of_availible_bags
+        //
+        // If no availible bag has any weight, e.g. every h is 0, the
+        // heuristic and then the pheromone terms are dropped in turn,
+        // down to a uniform choice, so tour construction never stalls.
+        // A custom selection rule replaces the weight, alpha is then unused
+        let weight = |bag: &usize, use_tau: bool, use_h: bool| -> f64 {
+            let h: f64 = if use_h && !self.disable_heuristic { self.graph[*bag].h } else { 1.0 };
+            match self.selection_rule {
+                Some(rule) => rule(if use_tau { self.tau.get_edge(*bag_i, *bag) } else { 1.0 }, h),
+                None => {
+                    let t: f64 = if use_tau { self.tau.get_edge(*bag_i, *bag).powf(alpha) } else { 1.0 };
+                    t * h
+                }
+            }
+        };
+        for (use_tau, use_h) in [(true, true), (true, false), (false, true), (false, false)] {
+            let sum_of_availible_bags: f64 = availible_bags
+                .iter()
+                .map(|bag| weight(bag, use_tau, use_h))
+                .sum::<f64>();
+            if sum_of_availible_bags > 0.0 {
+                // Compute the edge probability
+                return weight(bag_j, use_tau, use_h) / sum_of_availible_bags;
+            }
+        }
+        0.0
     }
 
     /// Evaporate pheromones from edges according to 
     /// the evaporation_rate. Evaporation rate is
     /// used as 1 - evaporation_rate
     pub fn evaporation_edges(&mut self, evaporation_rate: f64) {
-        for i in 0..self.graph.len()-1 {
-            for j in i+1..self.graph.len() {
+        for i in 0..self.nodes {
+            for j in i+1..self.nodes {
                 // Only evaporate bag edges
                 if i != j {
                     let value = self.tau.get_edge(i, j);
@@ -285,6 +953,23 @@ impl Graph {
         }
     }
 
+    /// Evaporates pheromones like evaporation_edges, but each edge at
+    /// evaporation_rate * (1 + gain * age), capped at 1, so edges that
+    /// have gone longer without a deposit are forgotten faster
+    /// ages: Number of updates since each edge last got a deposit
+    pub fn evaporation_edges_by_age(&mut self, evaporation_rate: f64, gain: f64, ages: &Tau) {
+        for i in 0..self.nodes {
+            for j in i+1..self.nodes {
+                let value = self.tau.get_edge(i, j);
+                // Very small values are left as they are, see evaporation_edges
+                if value > 0.0000000000000000000001 {
+                    let rate = (evaporation_rate * (1.0 + gain * ages.get_edge(i, j))).min(1.0);
+                    self.tau.set_edge(i, j, value * (1.0 - rate));
+                }
+            }
+        }
+    }
+
     /// Deposits pheromones additions on edges
     /// Heristic is based upon the ratio of cost-to-weight, 
     /// also used by KRZYSZTOF SCHIFF as 
@@ -297,64 +982,254 @@ impl Graph {
     /// The pheromone value is incremented by the tour's total cost divided by the tour's total weight.
     /// The tour's cost is multiplied by the pheromone weight, allowing for modification through
     /// experimeants without affecting the heristic's format.
-    pub fn deposit_phero(&mut self, edge: (usize, usize), tour_value: f64, tour_weight: f64, p_rate: f64) {
-        let value = (tour_value*p_rate) / tour_weight;
+    /// rule: Variant of the deposit, see DepositRule
+    pub fn deposit_phero(&mut self, edge: (usize, usize), tour_value: f64, tour_weight: f64, p_rate: f64, rule: DepositRule) {
+        let value = self.deposit_amount(tour_value, tour_weight, p_rate, rule);
         self.tau.add_to_edge(edge.0, edge.1, value);
     }
+
+    /// Returns the pheromone deposited on each edge of a tour, see deposit_phero
+    pub fn deposit_amount(&self, tour_value: f64, tour_weight: f64, p_rate: f64, rule: DepositRule) -> f64 {
+        let value = tour_value * p_rate;
+        match rule {
+            DepositRule::ValuePerWeight => value / tour_weight,
+            DepositRule::CapacityWeighted => value * tour_weight / self.total_capacity(),
+        }
+    }
+}
+
+/// Environment variable that sets the base directory relative input
+/// and output paths are resolved against, see base_dir
+pub const BASE_DIR_VAR: &str = "ACO_BASE_DIR";
+
+/// Path of the problem file, relative to the base directory
+const PROBLEM_FILE: &str = "src/BankProblem.txt";
+
+/// Returns the base directory relative paths are resolved against, so
+/// the program finds its files whatever directory it is run from.
+/// This is the ACO_BASE_DIR environment variable if set, otherwise the
+/// crate's directory at build time
+pub fn base_dir() -> PathBuf {
+    base_dir_from(std::env::var_os(BASE_DIR_VAR))
+}
+
+/// Returns the base directory given the value of ACO_BASE_DIR, see base_dir
+pub fn base_dir_from(variable: Option<OsString>) -> PathBuf {
+    variable
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")))
+}
+
+/// Returns the path of the default problem file, loaded unless another
+/// is given, PROBLEM_FILE resolved against the given base directory
+pub fn problem_file(base: &Path) -> PathBuf {
+    resolve_path(base, Path::new(PROBLEM_FILE))
+}
+
+/// Resolves a path against the base directory, absolute paths are
+/// returned unchanged
+pub fn resolve_path(base: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base.join(path)
+    }
 }
 
+/// Returns the blend of the component heuristics for a bag, see
+/// Graph::set_heuristic
+/// bag: Bag the heuristic is computed for
+/// terms: Components of the blend
+pub fn blended_heuristic(bag: &Bag, terms: &[HeuristicTerm]) -> f64 {
+    terms
+        .iter()
+        .map(|term| term.weight * term.heuristic.value(bag).powf(term.exponent))
+        .sum()
+}
+
+/// A loaded problem file as (every van's capacity, bags, numbers of
+/// the bags skipped as they had no value)
+type LoadedProblem = (Vec<f64>, Vec<Bag>, Vec<i64>);
+
 /// Loads data from the given text files.
 /// !!! IMPORTANT !!!
-/// 1. To run, ensure the path is the correct path to the problem's
-///    .txt file, otherwise the file cannot be read in and
+/// 1. To run, ensure PROBLEM_FILE is the path to the problem's .txt
+///    file relative to the base directory, see base_dir, otherwise the
+///    file cannot be read in and
 /// 2. Ensure the problem .txt file is in the exact format is was given
 ///    in the problem set.
-fn load_data(beta: f64) -> (f64, Vec<Bag>) {
-    let path = Path::new("src\\BankProblem.txt");
-    //let path = Path::new("/home/tomchambers/Documents/Exeter/409_aco/src/BankProblem.txt");
+///
+/// Returns the loaded problem, see LoadedProblem
+fn load_data(beta: f64, path: &Path) -> Result<LoadedProblem, GraphError> {
     println!("{:?}", path.to_str());
-    let data = fs::read_to_string(path).expect("Unable to read file");
+    load_file(path, beta, ParseMode::Lenient)
+}
+
+/// Loads the problem file at the given path, see load_data
+/// Returns the loaded problem, see LoadedProblem
+fn load_file(path: &Path, beta: f64, mode: ParseMode) -> Result<LoadedProblem, GraphError> {
+    let data = fs::read_to_string(path)
+        .map_err(|error| GraphError::Io(format!("{}: {}", path.display(), error)))?;
+    let (max_weight, bags, skipped_bags) = parse_data(&data, beta, mode)?;
+    if bags.is_empty() {
+        return Err(GraphError::EmptyProblem);
+    }
+    let mut capacities = parse_capacities(&data);
+    if capacities.is_empty() {
+        capacities.push(max_weight);
+    }
+    if let Some(capacity) = capacities.iter().find(|capacity| capacity.is_nan() || **capacity <= 0.0) {
+        return Err(GraphError::InvalidCapacity { capacity: *capacity });
+    }
+    Ok((capacities, bags, skipped_bags))
+}
+
+/// A problem file and whether it could be loaded into a graph
+pub type FileVerdict = (PathBuf, Result<(), GraphError>);
+
+/// Checks every .txt problem file in a directory can be loaded into
+/// a graph, without running the algorithm, so malformed files are
+/// caught before a long batch
+/// dir: Directory holding the problem files
+/// mode: How strictly each file is parsed, see ParseMode
+/// Returns the verdict of each file, in file name order, or a
+/// GraphError if the directory cannot be read
+pub fn validate_problem_files(dir: &Path, mode: ParseMode) -> Result<Vec<FileVerdict>, GraphError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|error| GraphError::Io(format!("{}: {}", dir.display(), error)))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let verdict = Graph::from_file_with_mode(&path, 1.0, mode).map(|_| ());
+            (path, verdict)
+        })
+        .collect())
+}
+
+/// Returns the first bag index that appears a second time in the tour,
+/// None if every bag is distinct
+pub fn first_duplicate(tour: &[usize]) -> Option<usize> {
+    let mut seen: Vec<usize> = Vec::with_capacity(tour.len());
+    for bag in tour {
+        if seen.contains(bag) {
+            return Some(*bag);
+        }
+        seen.push(*bag);
+    }
+    None
+}
+
+/// Returns true if the value is a whole number that fits in an i64
+fn is_integer(value: f64) -> bool {
+    value.fract() == 0.0 && value.abs() < i64::MAX as f64
+}
+
+/// Parses the capacity of every van in a problem file, for
+/// multi-knapsack problems with one capacity line per van.
+/// Commented out capacity lines are ignored
+fn parse_capacities(data: &str) -> Vec<f64> {
+    data.lines()
+        .filter_map(|line| parse_field(clean_line(line, ParseMode::Lenient), "security van capacity"))
+        .collect()
+}
 
-    let mut split_data: Vec<String> = data
+/// Trims a line of a problem file, dropping any '#' comment when
+/// parsing leniently
+fn clean_line(line: &str, mode: ParseMode) -> &str {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    match mode {
+        ParseMode::Strict => line.trim(),
+        ParseMode::Lenient => line.split('#').next().unwrap_or("").trim(),
+    }
+}
+
+/// Parses the contents of a problem file into the van's capacity
+/// and its bags.
+/// Bags with no value are dropped, as their heuristic is 0 so they
+/// would never be selected, and their numbers are returned so a run
+/// can warn of them. Bag numbers still follow the file, so a dropped
+/// bag leaves a gap in the numbering.
+/// mode: How strictly the file is parsed, see ParseMode
+/// Returns (capacity, bags, numbers of the skipped bags), or a GraphError
+/// if any bag has a negative value, or in strict mode if any line is
+/// unexpected
+fn parse_data(data: &str, beta: f64, mode: ParseMode) -> Result<(f64, Vec<Bag>, Vec<i64>), GraphError> {
+    // Blank lines never hold data, so are dropped in either mode,
+    // keeping the line number for errors
+    let split_data: Vec<(usize, &str)> = data
         .split('\n')
-        .map(|line| line.strip_suffix("\r").unwrap_or(line).trim().to_string())
+        .enumerate()
+        .map(|(i, line)| (i + 1, clean_line(line, mode)))
+        .filter(|(_, line)| !line.is_empty())
         .collect();
 
     let mut bags: Vec<Bag> = Vec::new();
+    let mut skipped_bags: Vec<i64> = Vec::new();
+    let mut max_weight: Option<f64> = None;
     let mut data_itre = split_data.iter();
 
     let mut number: i64 = 0;
-    while let Some(data_value) = data_itre.next() {
-        if data_value.contains("bag") {
+    while let Some((line_number, data_value)) = data_itre.next() {
+        if let Some(capacity) = parse_field(data_value, "security van capacity") {
+            max_weight.get_or_insert(capacity);
+        } else if data_value.to_lowercase().starts_with("bag") {
+            if mode == ParseMode::Strict && max_weight.is_none() {
+                return Err(GraphError::Parse("The first line has no valid security van capacity".to_string()));
+            }
             let weight = data_itre
                 .next()
-                .unwrap()
-                .strip_prefix("weight: ")
-                .unwrap()
-                .parse()
-                .unwrap();
+                .and_then(|(_, line)| parse_field(line, "weight"))
+                .ok_or_else(|| GraphError::Parse(format!("Bag {} has no valid weight", number)))?;
             let cost = data_itre
                 .next()
-                .unwrap()
-                .strip_prefix("value: ")
-                .unwrap()
-                .parse()
-                .unwrap();
-            let ratio = cost / weight;
-            bags.push(Bag {
-                number,
-                weight,
-                cost,
-                ratio,
-                h: ratio.powf(beta),
-            });
+                .and_then(|(_, line)| parse_field(line, "value"))
+                .ok_or_else(|| GraphError::Parse(format!("Bag {} has no valid value", number)))?;
+            if cost < 0.0 {
+                return Err(GraphError::NegativeCost { bag: number, cost });
+            }
+            if cost == 0.0 {
+                skipped_bags.push(number);
+            } else {
+                let ratio = cost / weight;
+                bags.push(Bag {
+                    number,
+                    weight,
+                    cost,
+                    ratio,
+                    h: ratio.powf(beta),
+                });
+            }
             number += 1;
+        } else if mode == ParseMode::Strict {
+            return Err(GraphError::Parse(format!("Line {} is unexpected: {}", line_number, data_value)));
         }
     }
-    (
-        split_data.remove(0).strip_prefix("security van capacity: ").unwrap().parse().unwrap(),
+    let max_weight = max_weight
+        .ok_or_else(|| GraphError::Parse("No line has a valid security van capacity".to_string()))?;
+    Ok((
+        max_weight,
         bags,
-    )
+        skipped_bags,
+    ))
+}
+
+/// Parses a "key: value" line into the value, if the line holds the
+/// given key. Keys are case-insensitive and any whitespace around
+/// the key, colon and value is ignored, so "weight:10", "weight :  10"
+/// and "Weight: 10" are all read as 10
+fn parse_field(line: &str, key: &str) -> Option<f64> {
+    let (line_key, value) = line.split_once(':')?;
+    if line_key.trim().eq_ignore_ascii_case(key) {
+        value.trim().parse().ok()
+    } else {
+        None
+    }
 }
 
 /// Mutli tests to ensure key functions within ACO work as intended.
@@ -366,7 +1241,7 @@ mod test  {
     use super::*;
     #[test]
     fn tau() {
-        let mut tau = Tau::new();
+        let mut tau = Tau::new(16);
         tau.set_edge(10, 15, 100.0);
         assert_eq!(tau.get_edge(10, 15), 100.0);
         assert_eq!(tau.get_edge(10, 15), tau.get_edge(15, 10));
@@ -374,6 +1249,34 @@ mod test  {
         assert_eq!(tau.get_edge(10, 15), 200.0);
     }
 
+    /// Tests merging pheromone matrices under each strategy
+    #[test]
+    fn tau_merge() {
+        let mut first = Tau::new(4);
+        first.set_edge(0, 1, 1.0);
+        first.set_edge(2, 3, 4.0);
+        let mut second = Tau::new(4);
+        second.set_edge(0, 1, 3.0);
+        second.set_edge(2, 3, 2.0);
+
+        let mut average = Tau::new(4);
+        average.merge(&[&first, &second], MergeStrategy::Average);
+        assert_eq!(average.get_edge(0, 1), 4.0 / 3.0);
+        assert_eq!(average.get_edge(3, 2), 2.0);
+
+        first.merge(&[&second], MergeStrategy::Max);
+        assert_eq!(first.get_edge(0, 1), 3.0);
+        assert_eq!(first.get_edge(2, 3), 4.0);
+        assert_eq!(first.get_edge(1, 2), 0.0);
+    }
+
+    /// Tests a pheromone matrix of another size is refused rather than partly merged
+    #[test]
+    #[should_panic(expected = "cannot merge a pheromone matrix of 5 bags into one of 4 bags")]
+    fn tau_merge_size_mismatch() {
+        Tau::new(4).merge(&[&Tau::new(5)], MergeStrategy::Max);
+    }
+
     /// Tests that the selection wheel correctly constructs and selects bags
     /// based on ranked probability selection.
     #[test]
@@ -423,4 +1326,556 @@ mod test  {
             .map(|(bag, _)| *bag).unwrap(), 
             4);            
     }
-}
\ No newline at end of file
+
+    /// Tests bags without value are skipped and negative values are rejected
+    #[test]
+    fn non_positive_costs() {
+        let data = "security van capacity: 50\nbag 1:\nweight: 10\nvalue: 20\nbag 2:\nweight: 5\nvalue: 0\nbag 3:\nweight: 5\nvalue: 10\n";
+        let (max_weight, bags, skipped_bags) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        assert_eq!(max_weight, 50.0);
+        assert_eq!(bags.len(), 2);
+        assert_eq!(bags.iter().map(|bag| bag.number).collect::<Vec<i64>>(), vec![0, 2]);
+        assert_eq!(skipped_bags, vec![1]);
+
+        let data = "security van capacity: 50\nbag 1:\nweight: 10\nvalue: -20\n";
+        assert_eq!(parse_data(data, 1.0, ParseMode::Lenient), Err(GraphError::NegativeCost { bag: 0, cost: -20.0 }));
+    }
+
+    /// Builds a graph from (weight, cost) pairs, with beta as 1
+    fn graph_from(max_weight: f64, bags: &[(f64, f64)]) -> Graph {
+        let bags: Vec<Bag> = bags
+            .iter()
+            .enumerate()
+            .map(|(number, (weight, cost))| Bag { number: number as i64, weight: *weight, cost: *cost, ratio: cost / weight, h: cost / weight })
+            .collect();
+        Graph::from_bags(max_weight, bags)
+    }
+
+    /// Tests bags with tied ratios are ranked by weight then bag number
+    #[test]
+    fn ratio_ties() {
+        let graph = graph_from(10.0, &[(10.0, 20.0), (5.0, 10.0), (4.0, 12.0), (5.0, 10.0), (1.0, 1.0)]);
+        assert_eq!(graph.indices_by_ratio(), vec![2, 1, 3, 0, 4]);
+    }
+
+    /// Tests random tours are feasible and their cost and weight add up
+    #[test]
+    fn random_feasible_tour() {
+        use rand::SeedableRng;
+        let graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (12.0, 1.0), (30.0, 100.0)]);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let (tour, cost, weight) = graph.random_feasible_tour(&mut rng);
+            assert!(!tour.is_empty() && !tour.contains(&4));
+            assert!(weight <= graph.max_weight());
+            assert_eq!(cost, tour.iter().map(|bag| graph.graph[*bag].cost).sum::<f64>());
+            assert_eq!(weight, tour.iter().map(|bag| graph.graph[*bag].weight).sum::<f64>());
+            // No bag left out of the tour still fits
+            assert!(graph.get_availible_bags(tour.last().unwrap(), &tour, graph.max_weight() - weight).is_empty());
+        }
+    }
+
+    /// Tests the problem summary of a small known problem
+    #[test]
+    fn problem_summary() {
+        let graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        assert_eq!(graph.summary(), ProblemSummary {
+            bags: 3,
+            capacity: 25.0,
+            total_weight: 19.0,
+            total_cost: 37.0,
+            min_ratio: 1.0,
+            max_ratio: 3.0,
+            mean_ratio: 2.0,
+            // Every bag fits at once, so the estimate is capped at the 3 bags
+            expected_tour_length: 3.0,
+        });
+    }
+
+    /// Tests the expected tour length is the capacity over the mean bag weight
+    #[test]
+    fn expected_tour_length() {
+        // Mean weight of 8, so 20 / 8 bags
+        let graph = graph_from(20.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (13.0, 13.0)]);
+        assert_eq!(graph.expected_tour_length(), 2.5);
+        assert_eq!(graph_from(20.0, &[]).expected_tour_length(), 0.0);
+    }
+
+    /// Tests fields parse with loose whitespace and any casing of the key
+    #[test]
+    fn field_parsing() {
+        assert_eq!(parse_field("weight:10", "weight"), Some(10.0));
+        assert_eq!(parse_field("weight :  10", "weight"), Some(10.0));
+        assert_eq!(parse_field("Weight: 10", "weight"), Some(10.0));
+        assert_eq!(parse_field("weight:\t10", "weight"), Some(10.0));
+        assert_eq!(parse_field("value: 10", "weight"), None);
+
+        let data = "Security Van Capacity:50\r\nBag 1:\r\nWeight :  10\r\nvalue:20\r\n";
+        let (max_weight, bags, _) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        assert_eq!(max_weight, 50.0);
+        assert_eq!((bags[0].weight, bags[0].cost), (10.0, 20.0));
+        assert!(matches!(parse_data("security van capacity: 50\nbag 1:\nweight: ten\n", 1.0, ParseMode::Lenient), Err(GraphError::Parse(_))));
+    }
+
+    /// Tests bag numbers map to indices once a dropped bag makes them differ
+    #[test]
+    fn bag_number_mapping() {
+        let data = "security van capacity: 50\nbag 1:\nweight: 10\nvalue: 20\nbag 2:\nweight: 5\nvalue: 0\nbag 3:\nweight: 5\nvalue: 10\n";
+        let (max_weight, bags, _) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        let graph = Graph::from_bags(max_weight, bags);
+        assert_eq!(graph.index_of(0), Some(0));
+        assert_eq!(graph.index_of(1), None);
+        assert_eq!(graph.index_of(2), Some(1));
+        assert_eq!(graph.number_at(1), 2);
+        assert_eq!(graph.number_at(graph.index_of(2).unwrap()), 2);
+    }
+
+    /// Tests the DOT export only draws edges above the threshold
+    #[test]
+    fn dot_export() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        graph.tau.set_edge(0, 1, 2.0);
+        graph.tau.set_edge(1, 2, 1.0);
+        graph.tau.set_edge(0, 2, 0.1);
+        let dot = graph.to_dot(0.5);
+        assert_eq!(dot, "graph aco {\n    0 -- 1 [penwidth=5.000];\n    1 -- 2 [penwidth=2.500];\n}\n");
+        assert_eq!(graph.to_dot(10.0), "graph aco {\n}\n");
+    }
+
+    /// Tests sorting by ratio keeps tours and selection probabilities the same by bag number
+    #[test]
+    fn sort_by_ratio() {
+        let bags = [(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (12.0, 1.0), (3.0, 9.0)];
+        let mut unsorted = graph_from(25.0, &bags);
+        let mut sorted = graph_from(25.0, &bags);
+        for i in 0..bags.len() {
+            for j in i+1..bags.len() {
+                unsorted.tau.set_edge(i, j, (i + 2 * j) as f64);
+                sorted.tau.set_edge(i, j, (i + 2 * j) as f64);
+            }
+        }
+        sorted.sort_by_ratio();
+
+        assert_eq!(sorted.graph.iter().map(|bag| bag.number).collect::<Vec<i64>>(), vec![4, 2, 0, 1, 3]);
+        assert_eq!(sorted.summary().total_cost, unsorted.summary().total_cost);
+        for number_i in 0..bags.len() as i64 {
+            let (sorted_i, unsorted_i) = (sorted.index_of(number_i).unwrap(), unsorted.index_of(number_i).unwrap());
+            let sorted_availible = sorted.get_availible_bags(&sorted_i, &[sorted_i], 25.0);
+            let unsorted_availible = unsorted.get_availible_bags(&unsorted_i, &[unsorted_i], 25.0);
+            for number_j in (0..bags.len() as i64).filter(|number| *number != number_i) {
+                let (sorted_j, unsorted_j) = (sorted.index_of(number_j).unwrap(), unsorted.index_of(number_j).unwrap());
+                assert_eq!(sorted.tau.get_edge(sorted_i, sorted_j), unsorted.tau.get_edge(unsorted_i, unsorted_j));
+                let sorted_p = sorted.calculate_edge_probability(&sorted_i, &sorted_j, &sorted_availible, 1.0);
+                let unsorted_p = unsorted.calculate_edge_probability(&unsorted_i, &unsorted_j, &unsorted_availible, 1.0);
+                assert!((sorted_p - unsorted_p).abs() < 1e-12);
+            }
+        }
+    }
+
+    /// Tests a file with comments, junk and blank sections parses leniently but not strictly
+    #[test]
+    fn parse_modes() {
+        let data = "# problem notes\n\nbag 1:\nweight: 10 # heavy\nvalue: 20\n\n\nsecurity van capacity: 50\nbag 2:\nweight: 5\nvalue: 10\nend of file\n";
+        let (max_weight, bags, _) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        assert_eq!(max_weight, 50.0);
+        assert_eq!(bags.iter().map(|bag| (bag.weight, bag.cost)).collect::<Vec<(f64, f64)>>(), vec![(10.0, 20.0), (5.0, 10.0)]);
+        assert_eq!(parse_capacities("# security van capacity: 10\nsecurity van capacity: 50\n"), vec![50.0]);
+
+        assert_eq!(
+            parse_data(data, 1.0, ParseMode::Strict),
+            Err(GraphError::Parse("Line 1 is unexpected: # problem notes".to_string()))
+        );
+        let data = "security van capacity: 50\n\nbag 1:\nweight: 10\nvalue: 20\nend of file\n";
+        assert_eq!(
+            parse_data(data, 1.0, ParseMode::Strict),
+            Err(GraphError::Parse("Line 6 is unexpected: end of file".to_string()))
+        );
+        assert!(parse_data("security van capacity: 50\n\nbag 1:\nweight: 10\nvalue: 20\n", 1.0, ParseMode::Strict).is_ok());
+        assert!(matches!(parse_data("bag 1:\nweight: 10\nvalue: 20\nsecurity van capacity: 50\n", 1.0, ParseMode::Strict), Err(GraphError::Parse(_))));
+    }
+
+    /// Tests every van's capacity line is read
+    #[test]
+    fn capacities() {
+        let data = "security van capacity: 50\nsecurity van capacity: 20\nbag 1:\nweight: 10\nvalue: 20\n";
+        assert_eq!(parse_capacities(data), vec![50.0, 20.0]);
+        assert_eq!(parse_data(data, 1.0, ParseMode::Lenient).unwrap().0, 50.0);
+    }
+
+    /// Tests the edge probability matches tau^alpha * h / sum for a known configuration
+    #[test]
+    fn edge_probability() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        graph.tau.set_edge(0, 1, 2.0);
+        graph.tau.set_edge(0, 2, 4.0);
+        // h is the ratio, 1 for bag 1 and 3 for bag 2
+        let availible_bags = vec![1, 2];
+        let sum = 2.0_f64.powf(2.0) * 1.0 + 4.0_f64.powf(2.0) * 3.0;
+        assert_eq!(graph.edge_probability(0, 1, &availible_bags, 2.0), 4.0 / sum);
+        assert_eq!(graph.edge_probability(0, 2, &availible_bags, 2.0), 48.0 / sum);
+    }
+
+    /// Tests a custom selection rule replaces tau^alpha * h in the wheel
+    #[test]
+    fn custom_selection_rule() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        graph.tau.set_edge(0, 1, 2.0);
+        graph.tau.set_edge(0, 2, 4.0);
+        graph.selection_rule = Some(|tau, h| tau + h);
+        // h is 1 for bag 1 and 3 for bag 2, so the weights are 3 and 7
+        assert_eq!(graph.edge_probability(0, 1, &[1, 2], 2.0), 0.3);
+        assert_eq!(graph.edge_probability(0, 2, &[1, 2], 2.0), 0.7);
+        assert_eq!(graph.create_selection_wheel(&0, &[1, 2], 2.0), vec![0.3, 1.0]);
+    }
+
+    /// Tests selection falls back to pheromone only when every availible bag has no heuristic
+    #[test]
+    fn zero_heuristic() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        graph.graph.iter_mut().for_each(|bag| bag.h = 0.0);
+        graph.tau.set_edge(0, 1, 1.0);
+        graph.tau.set_edge(0, 2, 3.0);
+        assert_eq!(graph.edge_probability(0, 1, &[1, 2], 1.0), 0.25);
+        assert_eq!(graph.edge_probability(0, 2, &[1, 2], 1.0), 0.75);
+        assert!(graph.select_path(&0, &[1, 2], 1.0, &mut rand::thread_rng()).is_some());
+        // With no pheromones either the choice is uniform
+        graph.tau = Tau::new(graph.nodes);
+        assert_eq!(graph.edge_probability(0, 2, &[1, 2], 1.0), 0.5);
+        assert!(graph.select_path(&0, &[1, 2], 1.0, &mut rand::thread_rng()).is_some());
+    }
+
+    /// Tests validating a directory reports each problem file's verdict
+    #[test]
+    fn validate_problem_files() {
+        let dir = std::env::temp_dir().join(format!("aco_validate_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("good.txt"), "security van capacity: 50\nbag 1:\nweight: 10\nvalue: 20\n").unwrap();
+        fs::write(dir.join("malformed.txt"), "security van capacity: 50\nbag 1:\nweight: ten\nvalue: 20\n").unwrap();
+        fs::write(dir.join("notes.md"), "not a problem file").unwrap();
+
+        let verdicts = super::validate_problem_files(&dir, ParseMode::Strict).unwrap();
+        assert_eq!(verdicts.len(), 2);
+        assert_eq!(verdicts[0], (dir.join("good.txt"), Ok(())));
+        assert_eq!(verdicts[1].0, dir.join("malformed.txt"));
+        assert!(matches!(verdicts[1].1, Err(GraphError::Parse(_))));
+        assert!(matches!(Graph::from_file(&dir.join("missing.txt"), 1.0), Err(GraphError::Io(_))));
+
+        // Empty files and files with no valuable bags are errors rather than empty graphs
+        fs::write(dir.join("empty.txt"), "").unwrap();
+        assert!(matches!(Graph::from_file(&dir.join("empty.txt"), 1.0), Err(GraphError::Parse(_))));
+        fs::write(dir.join("no_bags.txt"), "security van capacity: 50\nbag 1:\nweight: 10\nvalue: 0\n").unwrap();
+        assert_eq!(Graph::from_file(&dir.join("no_bags.txt"), 1.0).unwrap_err(), GraphError::EmptyProblem);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests relative paths resolve against the base directory, not the working directory
+    #[test]
+    fn base_directory() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        assert_eq!(base_dir_from(None), manifest_dir);
+        assert_eq!(base_dir_from(Some(OsString::new())), manifest_dir);
+        let custom = std::env::temp_dir().join("aco_base");
+        assert_eq!(base_dir_from(Some(custom.clone().into_os_string())), custom);
+
+        let problem = resolve_path(&manifest_dir, Path::new(PROBLEM_FILE));
+        assert!(problem.is_absolute());
+        assert_eq!(problem, manifest_dir.join("src").join("BankProblem.txt"));
+        assert_eq!(resolve_path(&custom, Path::new("csv")), custom.join("csv"));
+        assert_eq!(resolve_path(&custom, &manifest_dir), manifest_dir);
+        // The base is absolute, so resolved paths never depend on the working directory
+        assert!(base_dir().is_absolute());
+        assert!(resolve_path(&base_dir(), Path::new("csv")).is_absolute());
+    }
+
+    /// Tests alpha of 0 ignores the pheromones and beta of 0 ignores the heuristic
+    #[test]
+    fn zero_alpha_and_beta() {
+        let data = "security van capacity: 50\nbag 1:\nweight: 10\nvalue: 20\nbag 2:\nweight: 5\nvalue: 5\nbag 3:\nweight: 4\nvalue: 12\n";
+        let availible_bags = vec![1, 2];
+
+        // Pure heuristic, only the ratios of 1 and 3 matter whatever the pheromones
+        let (max_weight, bags, _) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        let mut graph = Graph::from_bags(max_weight, bags);
+        for (edge_1, edge_2) in [(1.0, 1.0), (2.0, 0.5), (100.0, 0.01)] {
+            graph.tau.set_edge(0, 1, edge_1);
+            graph.tau.set_edge(0, 2, edge_2);
+            assert!((graph.edge_probability(0, 2, &availible_bags, 0.0) - 0.75).abs() < 1e-12);
+        }
+
+        // Pure pheromone, every h is 1 so only the pheromones matter
+        let (max_weight, bags, _) = parse_data(data, 0.0, ParseMode::Lenient).unwrap();
+        let mut graph = Graph::from_bags(max_weight, bags);
+        assert!(graph.graph.iter().all(|bag| bag.h == 1.0));
+        graph.tau.set_edge(0, 1, 3.0);
+        graph.tau.set_edge(0, 2, 1.0);
+        assert!((graph.edge_probability(0, 1, &availible_bags, 1.0) - 0.75).abs() < 1e-12);
+    }
+
+    /// Tests integer weights sum exactly and fill a van to its exact capacity despite drift
+    #[test]
+    fn integer_weights() {
+        let data = "security van capacity: 30\nbag 1:\nweight: 10\nvalue: 20\nbag 2:\nweight: 10\nvalue: 5\nbag 3:\nweight: 10\nvalue: 12\n";
+        let (max_weight, bags, _) = parse_data(data, 1.0, ParseMode::Lenient).unwrap();
+        let graph = Graph::from_bags(max_weight, bags);
+        assert!(graph.integer_weights);
+        let (tour, _, weight) = graph.random_feasible_tour(&mut rand::thread_rng());
+        assert_eq!((tour.len(), weight), (3, 30.0));
+
+        // A running total that drifted just under the capacity still admits the last bag
+        let drifted = 30.0 - (0.1 + 0.2 - 0.3) * 1e4 - 20.0;
+        assert!(drifted < 10.0);
+        assert_eq!(graph.get_availible_bags(&0, &[0, 1], drifted), vec![2]);
+
+        let fractional = graph_from(30.0, &[(10.5, 20.0), (10.0, 5.0)]);
+        assert!(!fractional.integer_weights);
+        assert!(fractional.get_availible_bags(&1, &[1], 10.4999).is_empty());
+    }
+
+    /// Tests availible bags are in ascending index order with a candidate list restricting them
+    #[test]
+    fn availible_bags_order() {
+        let mut graph = graph_from(50.0, &[(10.0, 10.0), (5.0, 20.0), (4.0, 4.0), (5.0, 25.0), (2.0, 10.0)]);
+        assert_eq!(graph.get_availible_bags(&0, &[0], 50.0), vec![1, 2, 3, 4]);
+
+        // Ranked by ratio the bags are 4, 3, 1, 2, 0
+        graph.candidate_list = Some(3);
+        assert_eq!(graph.get_availible_bags(&0, &[0], 50.0), vec![1, 3, 4]);
+        assert_eq!(graph.get_availible_bags(&4, &[1, 3, 4], 50.0), vec![0, 2]);
+    }
+
+    /// Tests the candidate list holds the highest ratio bags not yet visited, as bags get visited
+    #[test]
+    fn candidate_list() {
+        let mut graph = graph_from(50.0, &[(10.0, 10.0), (5.0, 20.0), (4.0, 4.0), (5.0, 25.0), (2.0, 10.0)]);
+        graph.candidate_list = Some(2);
+        assert_eq!(graph.ratio_order, vec![4, 3, 1, 2, 0]);
+        assert_eq!(graph.get_availible_bags(&2, &[2], 50.0), vec![3, 4]);
+        assert_eq!(graph.get_availible_bags(&3, &[2, 3], 50.0), vec![1, 4]);
+        assert_eq!(graph.get_availible_bags(&4, &[2, 3, 4], 50.0), vec![0, 1]);
+        // Bag 1 does not fit, so the next bag in ratio order takes its place
+        assert_eq!(graph.get_availible_bags(&4, &[3, 4], 4.0), vec![2]);
+        assert_eq!(graph.get_availible_bags(&4, &[0, 1, 2, 3, 4], 50.0), Vec::<usize>::new());
+
+        graph.sort_by_ratio();
+        assert_eq!(graph.ratio_order, vec![0, 1, 2, 3, 4]);
+        assert_eq!(graph.get_availible_bags(&0, &[0], 50.0), vec![1, 2]);
+    }
+
+    /// Tests the pheromone statistics of a hand built matrix
+    #[test]
+    fn pheromone_stats() {
+        let mut tau = Tau::new(3);
+        tau.set_edge(0, 1, 2.0);
+        tau.set_edge(0, 2, 4.0);
+        tau.set_edge(1, 2, 0.0);
+        assert_eq!(tau.stats(), PheromoneStats { min: 0.0, max: 4.0, mean: 2.0, nonzero: 2 });
+        assert_eq!(Tau::new(0).stats().nonzero, 0);
+    }
+
+    /// Tests a near full tour deposits more than a half full one of equal cost under the capacity weighted deposit
+    #[test]
+    fn capacity_weighted_deposit() {
+        let graph = graph_from(100.0, &[(10.0, 10.0)]);
+        let deposit = |weight: f64, rule: DepositRule| graph.deposit_amount(200.0, weight, 1.0, rule);
+        assert_eq!(deposit(90.0, DepositRule::CapacityWeighted), 200.0 * 0.9);
+        assert_eq!(deposit(50.0, DepositRule::CapacityWeighted), 200.0 * 0.5);
+        assert!(deposit(90.0, DepositRule::CapacityWeighted) > deposit(50.0, DepositRule::CapacityWeighted));
+        // Value per weight favours the lighter tour instead
+        assert!(deposit(50.0, DepositRule::ValuePerWeight) > deposit(90.0, DepositRule::ValuePerWeight));
+    }
+
+    /// Tests evaluating a tour sums its bags and rejects a repeated bag
+    #[test]
+    fn evaluate_tour() {
+        let graph = graph_from(50.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        assert_eq!(graph.evaluate_tour(&[0, 2]), Ok((32.0, 14.0)));
+        assert_eq!(graph.evaluate_tour(&[0, 2, 1, 2]), Err(GraphError::DuplicateBag { bag: 2 }));
+        assert_eq!(first_duplicate(&[1, 0, 1]), Some(1));
+    }
+
+    /// Tests pheromone looked up by bag numbers matches the index based edge
+    #[test]
+    fn pheromone_between() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        graph.graph[1].number = 7;
+        graph.graph[2].number = 9;
+        graph.tau.set_edge(1, 2, 0.75);
+        assert_eq!(graph.pheromone_between(7, 9), Some(graph.tau.get_edge(1, 2)));
+        assert_eq!(graph.pheromone_between(9, 7), Some(0.75));
+        assert_eq!(graph.pheromone_between(7, 2), None);
+    }
+
+    /// Tests the ndarray pheromone field is symmetric and matches each edge
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn pheromone_ndarray() {
+        let mut tau = Tau::new(5);
+        for i in 0..5 {
+            for j in i+1..5 {
+                tau.set_edge(i, j, (i * 5 + j) as f64);
+            }
+        }
+        let field = tau.to_ndarray();
+        assert_eq!(field.dim(), (5, 5));
+        assert_eq!(field, field.t());
+        for (i, j) in [(0, 1), (3, 1), (2, 4), (4, 0)] {
+            assert_eq!(field[[i, j]], tau.get_edge(i, j));
+        }
+        assert_eq!(field[[2, 2]], 0.0);
+    }
+
+    /// Tests heuristics are computed once per beta and switching beta sets the bags' h
+    #[test]
+    fn heuristic_for_beta() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0)]);
+        let first = graph.heuristic_for_beta(2.0).as_ptr();
+        assert_eq!(graph.heuristic_for_beta(2.0).as_ptr(), first);
+        assert_eq!(graph.heuristic_for_beta(2.0), [4.0, 1.0, 9.0]);
+        assert_eq!(graph.heuristic_for_beta(0.0), [1.0, 1.0, 1.0]);
+        assert_eq!(graph.heuristic_cache.len(), 2);
+
+        graph.set_beta(2.0);
+        assert_eq!(graph.graph.iter().map(|bag| bag.h).collect::<Vec<f64>>(), vec![4.0, 1.0, 9.0]);
+        graph.sort_by_ratio();
+        assert!(graph.heuristic_cache.is_empty());
+        assert_eq!(graph.heuristic_for_beta(1.0), [3.0, 2.0, 1.0]);
+    }
+
+    /// Tests the LP bound takes the best ratio bags whole and the next one in part
+    #[test]
+    fn lp_upper_bound() {
+        let graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (12.0, 1.0)]);
+        // Bags 2, 0 and 1 fit whole, leaving room for 6 of bag 3's 12 weight
+        assert_eq!(graph.lp_upper_bound(), 12.0 + 20.0 + 5.0 + 1.0 * 6.0 / 12.0);
+        assert!(graph.lp_upper_bound() >= graph.greedy_solution().1);
+    }
+
+    /// Tests problem files of any number of bags load with a pheromone matrix of their size
+    #[test]
+    fn bag_count() {
+        let dir = std::env::temp_dir().join(format!("aco_bag_count_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let problem = |bags: usize| {
+            let bags: String = (1..=bags).map(|bag| format!("bag {}:\nweight: 5\nvalue: {}\n", bag, bag)).collect();
+            format!("security van capacity: 50\n{}", bags)
+        };
+        fs::write(dir.join("seven.txt"), problem(7)).unwrap();
+        let mut graph = Graph::from_file(&dir.join("seven.txt"), 1.0).unwrap();
+        graph.initialize_tau(&mut rand::thread_rng());
+        assert_eq!(graph.nodes, 7);
+        assert_eq!(graph.tau.size(), 7);
+        assert!(graph.tau.get_edge(0, 6) > 0.0);
+
+        fs::write(dir.join("many.txt"), problem(500)).unwrap();
+        let mut graph = Graph::from_file(&dir.join("many.txt"), 1.0).unwrap();
+        assert_eq!(graph.tau.size(), 500);
+        graph.initialize_tau(&mut rand::thread_rng());
+        let before = graph.tau.get_edge(0, 499);
+        graph.evaporation_edges(0.5);
+        assert_eq!(graph.tau.get_edge(0, 499), before * 0.5);
+        graph.sort_by_ratio();
+        assert_eq!(graph.tau.size(), 500);
+
+        // An empty problem has nothing to evaporate
+        Graph::from_bags(10.0, Vec::new()).evaporation_edges(0.5);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Tests the debug wheel matches the edge probabilities and its cumulative value ends at 1
+    #[test]
+    fn debug_wheel() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (3.0, 9.0)]);
+        graph.tau.set_edge(0, 1, 2.0);
+        graph.tau.set_edge(0, 2, 0.5);
+        graph.tau.set_edge(0, 3, 1.0);
+        let availible = [1, 2, 3];
+        let wheel = graph.debug_wheel(0, &availible, 1.0);
+        assert_eq!(wheel.iter().map(|(bag, _, _)| *bag).collect::<Vec<usize>>(), vec![1, 2, 3]);
+        for (bag, probability, _) in &wheel {
+            assert_eq!(*probability, graph.calculate_edge_probability(&0, bag, &availible, 1.0));
+        }
+        assert!((wheel.iter().map(|(_, probability, _)| probability).sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!((wheel[2].2 - 1.0).abs() < 1e-12);
+        assert_eq!(wheel[1].2, wheel[0].1 + wheel[1].1);
+    }
+
+    /// Tests a blended heuristic is the weighted sum of its components
+    #[test]
+    fn blended_heuristic() {
+        let mut graph = graph_from(25.0, &[(4.0, 12.0), (5.0, 5.0)]);
+        let terms = [
+            HeuristicTerm { heuristic: Heuristic::Ratio, weight: 0.75, exponent: 2.0 },
+            HeuristicTerm { heuristic: Heuristic::InverseWeight, weight: 0.25, exponent: 0.5 },
+        ];
+        graph.set_heuristic(&terms);
+        assert_eq!(graph.graph[0].h, 0.75 * 3.0_f64.powf(2.0) + 0.25 * 0.25_f64.powf(0.5));
+        assert_eq!(graph.graph[1].h, 0.75 + 0.25 * 0.2_f64.powf(0.5));
+
+        // A lone ratio term matches the beta heuristic
+        graph.set_heuristic(&[HeuristicTerm { heuristic: Heuristic::Ratio, weight: 1.0, exponent: 2.0 }]);
+        let h: Vec<f64> = graph.graph.iter().map(|bag| bag.h).collect();
+        assert_eq!(h, graph.heuristic_for_beta(2.0));
+    }
+
+    /// Tests bags with no probability on the wheel are never chosen, even on a tie
+    #[test]
+    fn zero_probability_skipped() {
+        let availible = [4, 7, 2, 9];
+        let wheel = [0.0, 0.25, 0.25, 1.0];
+        assert_eq!(Graph::spin_wheel(&availible, &wheel, 0.0), Some(7));
+        assert_eq!(Graph::spin_wheel(&availible, &wheel, 0.25), Some(7));
+        assert_eq!(Graph::spin_wheel(&availible, &wheel, 0.3), Some(9));
+
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (3.0, 9.0)]);
+        graph.tau.set_edge(0, 1, 1.0);
+        graph.tau.set_edge(0, 2, 0.0);
+        graph.tau.set_edge(0, 3, 1.0);
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for _ in 0..1000 {
+            assert_ne!(graph.select_path(&0, &[1, 2, 3], 1.0, &mut rng), Some(2));
+        }
+    }
+
+    /// Tests selection only depends on the pheromones when the heuristic is disabled
+    #[test]
+    fn disable_heuristic() {
+        let mut graph = graph_from(25.0, &[(10.0, 20.0), (5.0, 5.0), (4.0, 12.0), (3.0, 9.0)]);
+        graph.tau.set_edge(0, 1, 1.0);
+        graph.tau.set_edge(0, 2, 3.0);
+        graph.tau.set_edge(0, 3, 4.0);
+        let availible = [1, 2, 3];
+        graph.disable_heuristic = true;
+        let probabilities: Vec<f64> = availible.iter().map(|bag| graph.edge_probability(0, *bag, &availible, 1.0)).collect();
+        assert_eq!(probabilities, vec![0.125, 0.375, 0.5]);
+
+        // Changing the heuristic leaves selection unchanged
+        graph.set_beta(3.0);
+        graph.graph[2].h = 0.0;
+        let reweighted: Vec<f64> = availible.iter().map(|bag| graph.edge_probability(0, *bag, &availible, 1.0)).collect();
+        assert_eq!(reweighted, probabilities);
+
+        graph.disable_heuristic = false;
+        assert_eq!(graph.edge_probability(0, 2, &availible, 1.0), 0.0);
+    }
+
+    /// Tests a problem with a van capacity of zero or less is an error rather than a run of empty tours
+    #[test]
+    fn invalid_capacity() {
+        let dir = std::env::temp_dir().join(format!("aco_invalid_capacity_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("zero.txt"), "security van capacity: 0\nbag 1:\nweight: 10\nvalue: 20\n").unwrap();
+        assert_eq!(
+            Graph::construct_graph(1.0, &dir.join("zero.txt")).unwrap_err(),
+            GraphError::InvalidCapacity { capacity: 0.0 }
+        );
+        fs::write(dir.join("negative.txt"), "security van capacity: -5\nbag 1:\nweight: 10\nvalue: 20\n").unwrap();
+        assert_eq!(
+            Graph::from_file_with_mode(&dir.join("negative.txt"), 1.0, ParseMode::Strict).unwrap_err(),
+            GraphError::InvalidCapacity { capacity: -5.0 }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,22 +1,185 @@
-use std::collections::HashMap;
-use std::fs::OpenOptions;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::error::Error;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 // Handles CLI inputs
 use dialoguer::{theme::ColorfulTheme, Input, Select};
+use serde::Deserialize;
 // Delcares mods for use in modules
 pub mod algorithm;
 pub mod graph;
 pub mod ant;
 pub mod research_set;
+pub mod statistics;
+pub mod results_diff;
+#[cfg(feature = "parquet")]
+pub mod parquet_output;
+#[cfg(feature = "plot")]
+pub mod plot;
 use research_set::ResearchSet;
+use graph::{GraphError, ParseMode};
+use algorithm::RunResults;
 
-/// Static to track csv creation as to not overwrite the csv headers
-/// !!! Important !!!
-/// If the csv file has data written which should not be overwritten
-/// set this too true, then all data will be appended and the headers
-/// will not be changed and re-written
-static mut CSV_INITILIZED: bool = true;
+/// Directory the DEFAULT and EXPERIMENT results are written to, relative
+/// to the base directory, see graph::base_dir. Created if it does not exist
+const OUTPUT_DIR: &str = "csv";
+
+/// If true, a timestamp is appended to each results file name so
+/// every run of the program writes to its own files instead of
+/// appending to the previous results
+const TIMESTAMP_FILENAMES: bool = false;
+
+/// Layout of the results csv. Edit the columns to write only the
+/// metrics an analysis needs. With metadata on, read the files with
+/// a comment character, e.g. pandas.read_csv(path, comment="#")
+const CSV_LAYOUT: CsvLayout = CsvLayout { columns: &Column::ALL, metadata: false };
+
+/// Known optimal value of the problem, if any. When set, the optimality
+/// gap of each run's final score is written to the Optimality_Gap column
+const KNOWN_OPTIMUM: Option<f64> = None;
+
+/// What each run's final score is normalized by, if anything. When set,
+/// the normalized score is written to the Normalized_Score column so
+/// results of different problems can be aggregated
+const SCORE_NORMALIZATION: Option<algorithm::Normalization> = None;
+
+/// Base seed of the DEFAULT, CUSTOM and EXPERIMENT runs. When set, each
+/// run is seeded with algorithm::derive_seed of the base seed, its
+/// parameter setting and its run number, so the whole sweep can be
+/// reproduced. When None, runs are seeded from the OS
+const BASE_SEED: Option<u64> = None;
+
+/// Number of parameter settings of the EXPERIMENT sweeps run at once.
+/// Above 1 the sweeps, and the settings within them, run concurrently
+/// and the per run progress bars are hidden. Rows then reach each csv
+/// in the order the runs finish, the Parameter column tells them apart
+const EXPERIMENT_WORKERS: usize = 1;
+
+/// Most distinct results files an EXPERIMENT run may write. A sweep
+/// list that would create more is refused before any run starts, so a
+/// misconfigured sweep cannot fill the disk
+const MAX_OUTPUT_FILES: usize = 100;
+
+/// File name the best tour of every run is written to, next to the
+/// run's results csv. Each line holds the run id, the parameters, the
+/// tour's bag numbers, its cost and its weight, keeping the tours out of
+/// the metrics csv. None writes no solutions file
+const SOLUTIONS_FILE: Option<&str> = None;
+
+/// Held while a row is written, so concurrent runs writing to the same
+/// csv never interleave or both write its header
+static CSV_WRITE: Mutex<()> = Mutex::new(());
+
+/// Largest difference between a replayed final score and the recorded
+/// one that still counts as reproduced, see replay
+const REPLAY_TOLERANCE: f64 = 1e-6;
+
+/// Id of this invocation of the program, written on every csv row
+/// so results from many runs can be told apart once combined
+static RUN_ID: OnceLock<String> = OnceLock::new();
+
+/// Counter mixed into generated run ids, so ids generated within
+/// the same process are always distinct
+static RUN_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A column of the results csv
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Column {
+    RunId,
+    Parameter,
+    SweptValue,
+    Alpha,
+    Beta,
+    EvaporationRate,
+    PRate,
+    NumberOfAnts,
+    FitnessEvals,
+    InitialFitness,
+    InitialAvg,
+    TopFitness,
+    FinalAvg,
+    BestFitnessDifference,
+    AvgDifference,
+    OptimalityGap,
+    LastImprovement,
+    ImprovedOverGreedy,
+    UnusedCapacity,
+    SlackAdmitsBag,
+    Seed,
+    NormalizedScore,
+}
+
+impl Column {
+    /// Every column, in the default order
+    pub const ALL: [Column; 22] = [
+        Column::RunId,
+        Column::Parameter,
+        Column::SweptValue,
+        Column::Alpha,
+        Column::Beta,
+        Column::EvaporationRate,
+        Column::PRate,
+        Column::NumberOfAnts,
+        Column::FitnessEvals,
+        Column::InitialFitness,
+        Column::InitialAvg,
+        Column::TopFitness,
+        Column::FinalAvg,
+        Column::BestFitnessDifference,
+        Column::AvgDifference,
+        Column::OptimalityGap,
+        Column::LastImprovement,
+        Column::ImprovedOverGreedy,
+        Column::UnusedCapacity,
+        Column::SlackAdmitsBag,
+        Column::Seed,
+        Column::NormalizedScore,
+    ];
+
+    /// Returns the column's name in the csv header
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::RunId => "Run_Id",
+            Column::Parameter => "Parameter",
+            Column::SweptValue => "Swept_Value",
+            Column::Alpha => "Alpha",
+            Column::Beta => "Beta",
+            Column::EvaporationRate => "Evaporation_Rate",
+            Column::PRate => "p_rate",
+            Column::NumberOfAnts => "Number_Of_Ants",
+            Column::FitnessEvals => "Fitness_Evals",
+            Column::InitialFitness => "Initial_fitness",
+            Column::InitialAvg => "Initial_avg",
+            Column::TopFitness => "Top_Fitness",
+            Column::FinalAvg => "Final_avg",
+            Column::BestFitnessDifference => "Best_Fitness_Difference",
+            Column::AvgDifference => "Avg_Difference",
+            Column::OptimalityGap => "Optimality_Gap",
+            Column::LastImprovement => "Last_Improvement",
+            Column::ImprovedOverGreedy => "Improved_Over_Greedy",
+            Column::UnusedCapacity => "Unused_Capacity",
+            Column::SlackAdmitsBag => "Slack_Admits_Bag",
+            Column::Seed => "Seed",
+            Column::NormalizedScore => "Normalized_Score",
+        }
+    }
+}
+
+/// Layout of a results csv
+///     columns: Columns written, in order
+///     metadata: If true, new files start with a comment line recording
+///         the crate version, seed and creation time, before the header
+#[derive(Clone, Copy)]
+struct CsvLayout<'a> {
+    columns: &'a [Column],
+    metadata: bool,
+}
 
 /// Handles all parameter inputs and types of f64 | i64
 #[derive(Clone)]
@@ -29,6 +192,15 @@ pub enum Parameter {
     FitnessEvals(i64),
 }
 
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parameter::Alpha(val) | Parameter::Beta(val) | Parameter::EvaporationRate(val) | Parameter::PRate(val) => write!(f, "{}", val),
+            Parameter::NumOfAnts(val) | Parameter::FitnessEvals(val) => write!(f, "{}", val),
+        }
+    }
+}
+
 impl Parameter {
     /// Given a hashmap of parameters, extracts the params into the correctly formatted 
     /// collection of data types, in the order of 
@@ -67,8 +239,25 @@ impl Parameter {
 }
 
 fn main() {
+    // Jobs piped in by a scheduler skip the interactive menu
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--print-config") {
+        print!("{}", effective_config(&args, std::env::var_os(graph::BASE_DIR_VAR)));
+    }
+    let batch = batch_workers(&args).unwrap_or_else(|usage| {
+        eprintln!("{}", usage);
+        std::process::exit(2);
+    });
+    if let Some(workers) = batch {
+        let failed = run_batch(io::stdin().lock(), &mut io::stdout(), workers, |params| run(params, false, None, None));
+        if failed > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Constant choices for algorithm running
-    let choices = &["DEFAULT", "CUSTOM", "EXPERIMENT"];
+    let choices = &["DEFAULT", "CUSTOM", "EXPERIMENT", "VALIDATE", "COMPARE", "REPLAY"];
 
     let choice = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Enter values or run default")
@@ -88,58 +277,506 @@ fn main() {
             parameters.insert(String::from("num_of_ants"), Parameter::NumOfAnts(20));
             parameters.insert(String::from("fitness_evals"), Parameter::FitnessEvals(100));
             let number_of_runs: i64 = 1;
-            let path: &str = "csv/results.csv";
+            let path: String = output_path(&output_dir(), "results.csv", TIMESTAMP_FILENAMES)
+                .expect("Unable to create the output directory");
             // Runs algorithm with default params
             println!("Running with DEFAULT settings...");
-            run_experiment(&parameters, path, number_of_runs, 1);
+            run_setting(&parameters, &path, number_of_runs, &|params, seed| run(params, true, seed, None));
         },
         "EXPERIMENT" => {
             let number_of_runs: i64 = 5;
-            let mut path = "csv/results_ant_num.csv";            
-            
-            let experiment_params: Vec<HashMap<String, Parameter>> = ResearchSet::set_ant_number_params(vec![2,5,10,15,20,30,50,100]);
-            for (parameter_run, parameters) in experiment_params.into_iter().enumerate() {
-                run_experiment(&parameters, path, number_of_runs, parameter_run+1);
-            }
-            
-            path = "csv/results_evaporation.csv";
-            let experiment_params: Vec<HashMap<String, Parameter>> = ResearchSet::set_evaporation_params(vec![0.1,0.2,0.3,0.4,0.5,0.6,0.7,0.8]);
-            for (parameter_run, parameters) in experiment_params.into_iter().enumerate() {
-                run_experiment(&parameters, path, number_of_runs, parameter_run+1);
+            let sweeps = run_sweeps(experiment_sweeps(), &output_dir(), number_of_runs, EXPERIMENT_WORKERS, MAX_OUTPUT_FILES, |params, seed| {
+                run(params, EXPERIMENT_WORKERS == 1, seed, None)
+            });
+            if let Err(err) = sweeps {
+                eprintln!("{}", err);
+                std::process::exit(1);
             }
-
-            path = "csv/results_p_rate.csv";
-            let experiment_params: Vec<HashMap<String, Parameter>> = ResearchSet::set_p_rate_params(vec![0.5,1.0,2.0,3.0,4.0,5.0,6.0,7.0]);
-            for (parameter_run, parameters) in experiment_params.into_iter().enumerate() {
-                run_experiment(&parameters, path, number_of_runs, parameter_run+1);
-            }
-            
         },
         "CUSTOM" => {
             // User enters custom params with validation for data types
             let parameters = get_parameters();
             let number_of_runs: i64 = input_wrapper::<i64>("Enter the number of runs for the algorithm");
             let path: String = input_wrapper::<String>("Enter the CSV Path (with .csv as the suffix)");
+            let problem = optional_path_input("Enter the problem file path, or leave empty for the default");
             println!("Running with custome parameters...");
             // Runs algorithm with default params
-            run_experiment(&parameters, path.as_str(), number_of_runs, 1);
+            run_setting(&parameters, path.as_str(), number_of_runs, &|params, seed| run(params, true, seed, problem.as_deref()));
+        }
+        "VALIDATE" => {
+            // Checks every problem file in a directory parses, without running the algorithm
+            let dir: String = input_wrapper::<String>("Enter the directory of the problem files");
+            let strict: bool = input_wrapper::<bool>("Reject comments and unrecognised lines? (true/false)");
+            let mode = if strict { ParseMode::Strict } else { ParseMode::Lenient };
+            if !validate_problem_files(Path::new(&dir), mode, &mut io::stdout()) {
+                std::process::exit(1);
+            }
+        }
+        "COMPARE" => {
+            // Compares the results of a baseline against a modified algorithm, per parameter set
+            let baseline: String = input_wrapper::<String>("Enter the baseline CSV Path");
+            let candidate: String = input_wrapper::<String>("Enter the CSV Path to compare against the baseline");
+            let threshold: f64 = input_wrapper::<f64>("Enter the drop, as a fraction of the baseline, that counts as a regression");
+            match results_diff::diff_results(Path::new(&baseline), Path::new(&candidate), threshold) {
+                Ok(diffs) => {
+                    diffs.iter().for_each(|diff| println!("{}", diff));
+                    if diffs.iter().any(|diff| diff.has_regression()) {
+                        std::process::exit(1);
+                    }
+                }
+                Err(e) => {
+                    println!("Unable to compare the results: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        "REPLAY" => {
+            // Re-runs a recorded run with its parameters and seed, for investigating odd results
+            let path: String = input_wrapper::<String>("Enter the CSV Path");
+            let row_index: usize = input_wrapper::<usize>("Enter the row to replay, counted from 0 after the header");
+            match replay(Path::new(&path), row_index, |params, seed| run(params, true, seed, None)) {
+                Ok(results) => println!("Reproduced final score {}", results.final_score),
+                Err(e) => {
+                    println!("Unable to replay the run: {}", e);
+                    std::process::exit(1);
+                }
+            }
         }
         _ => unreachable!("Invalid selection"),
     }
 }
 
-fn run_experiment(parameters: &HashMap<String, Parameter>, path:&str, number_of_runs: i64, parameter_run: usize) {
-    for _ in 0..number_of_runs {
+/// Re-runs the run recorded in a row of a results csv, with the row's
+/// parameters and seed, and checks the final score is reproduced
+/// csv_path: Results csv with the parameter, Top_Fitness and Seed columns
+/// row_index: Row to replay, counted from 0 after the header
+/// runner: Runs the ACO once with the given parameters and seed, see run
+/// Returns the replayed run's results, or an error if the row cannot be
+/// read or its final score differs by more than REPLAY_TOLERANCE
+fn replay<F>(csv_path: &Path, row_index: usize, runner: F) -> Result<RunResults, Box<dyn Error>>
+where
+    F: Fn((f64, f64, f64, f64, i64, i64), Option<u64>) -> Result<RunResults, GraphError>,
+{
+    let mut rdr = csv::ReaderBuilder::new().comment(Some(b'#')).from_path(csv_path)?;
+    let headers = rdr.headers()?.clone();
+    let record = rdr
+        .records()
+        .nth(row_index)
+        .ok_or_else(|| format!("{} has no row {}", csv_path.display(), row_index))??;
+    let value = |column: Column| -> Result<&str, String> {
+        headers
+            .iter()
+            .position(|header| header == column.header())
+            .and_then(|i| record.get(i))
+            .filter(|value| !value.is_empty())
+            .ok_or_else(|| format!("row {} has no {}", row_index, column.header()))
+    };
+    let params = (
+        value(Column::Alpha)?.parse::<f64>()?,
+        value(Column::Beta)?.parse::<f64>()?,
+        value(Column::EvaporationRate)?.parse::<f64>()?,
+        value(Column::PRate)?.parse::<f64>()?,
+        value(Column::NumberOfAnts)?.parse::<i64>()?,
+        value(Column::FitnessEvals)?.parse::<i64>()?,
+    );
+    let seed = value(Column::Seed)?.parse::<u64>()?;
+    let recorded = value(Column::TopFitness)?.parse::<f64>()?;
+
+    let results = runner(params, Some(seed))?;
+    if (results.final_score - recorded).abs() > REPLAY_TOLERANCE {
+        return Err(format!("replayed final score {} does not match the recorded {}", results.final_score, recorded).into());
+    }
+    Ok(results)
+}
+
+/// Returns the configuration the program runs with, one "key: value"
+/// line per setting, once the command line arguments and the base
+/// directory variable have been applied over the constants, so the
+/// settings can be confirmed before a long run. Printed by --print-config
+/// args: Command line arguments, without the program name
+/// base_dir_var: Value of the ACO_BASE_DIR environment variable
+fn effective_config(args: &[String], base_dir_var: Option<std::ffi::OsString>) -> String {
+    let base_dir_source = match &base_dir_var {
+        Some(value) if !value.is_empty() => graph::BASE_DIR_VAR,
+        _ => "crate directory",
+    };
+    let base_dir = graph::base_dir_from(base_dir_var);
+    let none = || "none".to_string();
+    let batch = match batch_workers(args) {
+        Ok(Some(workers)) => format!("{} workers", workers),
+        Ok(None) => "off".to_string(),
+        Err(usage) => usage,
+    };
+    let columns: Vec<&str> = CSV_LAYOUT.columns.iter().map(Column::header).collect();
+    [
+        ("base_dir", format!("{} (from {})", base_dir.display(), base_dir_source)),
+        ("problem_file", graph::problem_file(&base_dir).display().to_string()),
+        ("output_dir", graph::resolve_path(&base_dir, Path::new(OUTPUT_DIR)).display().to_string()),
+        ("timestamp_filenames", TIMESTAMP_FILENAMES.to_string()),
+        ("solutions_file", SOLUTIONS_FILE.map_or_else(none, str::to_string)),
+        ("seed", BASE_SEED.map_or_else(none, |seed| format!("{} (derived per run)", seed))),
+        ("optimum", KNOWN_OPTIMUM.map_or_else(none, |optimum| optimum.to_string())),
+        ("experiment_workers", EXPERIMENT_WORKERS.to_string()),
+        ("max_output_files", MAX_OUTPUT_FILES.to_string()),
+        ("batch", batch),
+        ("csv_metadata", CSV_LAYOUT.metadata.to_string()),
+        ("csv_columns", columns.join(",")),
+    ]
+    .iter()
+    .map(|(key, value)| format!("{}: {}\n", key, value))
+    .collect()
+}
+
+/// Returns the number of workers if the arguments ask for the batch
+/// mode, as `--batch` with an optional `--jobs N`, 1 worker by default
+/// Returns a usage error if `--jobs` is not followed by a number
+fn batch_workers(args: &[String]) -> Result<Option<usize>, String> {
+    if !args.iter().any(|arg| arg == "--batch") {
+        return Ok(None);
+    }
+    let workers = match args.iter().position(|arg| arg == "--jobs") {
+        Some(i) => {
+            let value = args.get(i + 1).map_or("", String::as_str);
+            value
+                .parse::<usize>()
+                .map_err(|_| format!("Usage: --batch [--jobs N], the number of jobs must be a whole number, got {:?}", value))?
+        }
+        None => 1,
+    };
+    Ok(Some(workers.max(1)))
+}
+
+/// One job of the batch mode, read from one line of JSON such as
+/// {"alpha": 1.0, "beta": 2.0, "evaporation_rate": 0.1, "p_rate": 1.0,
+///  "num_of_ants": 20, "fitness_evals": 100, "output": "csv/job.csv"}
+///     output: Csv the job's results are appended to
+///     runs: Number of runs of the job, 1 if left out
+#[derive(Clone, Debug, Deserialize)]
+struct Job {
+    alpha: f64,
+    beta: f64,
+    evaporation_rate: f64,
+    p_rate: f64,
+    num_of_ants: i64,
+    fitness_evals: i64,
+    output: String,
+    #[serde(default = "Job::one_run")]
+    runs: i64,
+}
+
+impl Job {
+    fn one_run() -> i64 {
+        1
+    }
+
+    /// Returns the job's parameters in the order run takes them
+    fn params(&self) -> (f64, f64, f64, f64, i64, i64) {
+        (self.alpha, self.beta, self.evaporation_rate, self.p_rate, self.num_of_ants, self.fitness_evals)
+    }
+
+    /// Runs every run of the job, appending each to the job's output
+    fn execute<F>(&self, runner: &F) -> Result<(), Box<dyn Error>>
+    where
+        F: Fn((f64, f64, f64, f64, i64, i64)) -> Result<RunResults, GraphError>,
+    {
+        for _ in 0..self.runs {
+            let results = runner(self.params())?;
+            write_to_csv(&self.output, CSV_LAYOUT, self.params(), &results, 1, "")?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs every job read from input, one JSON job per line, writing a
+/// status line to out as each job finishes. Blank lines are skipped.
+/// Reading stops at a line that cannot be read, which is reported and
+/// counts as a failure, the jobs read before it still run.
+/// Jobs sharing an output file should not run on different workers,
+/// as their rows could interleave
+/// workers: Number of jobs run at once
+/// runner: Runs the ACO once with the given parameters, see run
+/// Returns the number of jobs that failed
+fn run_batch<I, W, F>(input: I, out: &mut W, workers: usize, runner: F) -> usize
+where
+    I: BufRead,
+    W: Write + Send,
+    F: Fn((f64, f64, f64, f64, i64, i64)) -> Result<RunResults, GraphError> + Sync,
+{
+    let mut lines: Vec<String> = Vec::new();
+    let mut unreadable = 0;
+    for (i, line) in input.lines().enumerate() {
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => lines.push(line),
+            Err(e) => {
+                let _ = writeln!(out, "Unable to read line {} of the jobs: {}", i + 1, e);
+                unreadable += 1;
+                break;
+            }
+        }
+    }
+    let next_job = Mutex::new(lines.iter().enumerate());
+    let out = Mutex::new(out);
+    let failed = Mutex::new(unreadable);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let Some((i, line)) = next_job.lock().unwrap().next() else { break };
+                let status = match serde_json::from_str::<Job>(line) {
+                    Ok(job) => job.execute(&runner)
+                        .map(|_| format!("Job {} done, {} runs written to {}", i + 1, job.runs, job.output))
+                        .map_err(|e| format!("Job {} failed: {}", i + 1, e)),
+                    Err(e) => Err(format!("Job {} failed: invalid job: {}", i + 1, e)),
+                };
+                if status.is_err() {
+                    *failed.lock().unwrap() += 1;
+                }
+                let status = status.unwrap_or_else(|e| e);
+                // Status lines are informational, a failed write should not stop the batch
+                let _ = writeln!(out.lock().unwrap(), "{}", status);
+            });
+        }
+    });
+    failed.into_inner().unwrap()
+}
+
+/// One sweep of an experiment, as (results file name, swept parameter, settings)
+type Sweep = (&'static str, &'static str, Vec<HashMap<String, Parameter>>);
+
+/// Returns the parameter sweeps of the EXPERIMENT mode, in the order they are run
+fn experiment_sweeps() -> Vec<Sweep> {
+    vec![
+        ("results_ant_num.csv", "num_of_ants", ResearchSet::set_ant_number_params(vec![2,5,10,15,20,30,50,100])),
+        ("results_evaporation.csv", "evaporation_rate", ResearchSet::set_evaporation_params(vec![0.1,0.2,0.3,0.4,0.5,0.6,0.7,0.8])),
+        ("results_p_rate.csv", "p_rate", ResearchSet::set_p_rate_params(vec![0.5,1.0,2.0,3.0,4.0,5.0,6.0,7.0])),
+    ]
+}
+
+/// Runs every parameter setting of the sweeps on a pool of workers,
+/// each sweep writing to its own csv in the output directory. Runs
+/// completed by an earlier, interrupted call are skipped, see
+/// run_experiment, and a csv's progress is cleared once every setting
+/// written to it has finished
+/// workers: Number of parameter settings run at once, 1 runs them in order
+/// max_output_files: Most distinct csvs the sweeps may write to
+/// runner: Runs the ACO once with the given parameters and seed, see run
+/// Returns an error, before anything is run or created, if the sweeps
+/// would write to more than max_output_files csvs
+fn run_sweeps<F>(sweeps: Vec<Sweep>, output_dir: &str, number_of_runs: i64, workers: usize, max_output_files: usize, runner: F) -> Result<(), Box<dyn Error>>
+where
+    F: Fn((f64, f64, f64, f64, i64, i64), Option<u64>) -> Result<RunResults, GraphError> + Sync,
+{
+    let mut file_names: Vec<&str> = sweeps.iter().map(|(file_name, _, _)| *file_name).collect();
+    file_names.sort_unstable();
+    file_names.dedup();
+    if file_names.len() > max_output_files {
+        return Err(format!(
+            "The experiment would write {} results files, more than the limit of {}, check the sweeps or raise MAX_OUTPUT_FILES",
+            file_names.len(), max_output_files
+        ).into());
+    }
+    // Progress is counted across all experiment files
+    let total_parameters = sweeps.iter().map(|(_, _, experiment_params)| experiment_params.len()).sum();
+    let mut settings = Vec::with_capacity(total_parameters);
+    for (sweep, (file_name, swept, experiment_params)) in sweeps.into_iter().enumerate() {
+        let path = output_path(output_dir, file_name, TIMESTAMP_FILENAMES)
+            .expect("Unable to create the output directory");
+        for (parameter_run, parameters) in experiment_params.into_iter().enumerate() {
+            let position = SweepPosition { sweep: sweep + 1, parameter: settings.len() + 1, total_parameters };
+            settings.push((path.clone(), swept, parameter_run + 1, parameters, position));
+        }
+    }
+    let next_setting = Mutex::new(settings.iter());
+    let unfinished: Mutex<HashSet<&String>> = Mutex::new(HashSet::new());
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            scope.spawn(|| loop {
+                let Some((path, swept, parameter_run, parameters, position)) = next_setting.lock().unwrap().next() else { break };
+                if !run_experiment(parameters, path, number_of_runs, *parameter_run, Some(swept), Some(*position), &runner) {
+                    unfinished.lock().unwrap().insert(path);
+                }
+            });
+        }
+    });
+    // A csv keeps its progress until every setting written to it has finished
+    let unfinished = unfinished.into_inner().unwrap();
+    let paths: HashSet<&String> = settings.iter().map(|(path, _, _, _, _)| path).collect();
+    paths.difference(&unfinished).for_each(|path| clear_progress(path));
+    Ok(())
+}
+
+/// Position of a parameter setting within a whole sweep, so long
+/// sweeps report how far along they are
+///     sweep: Number of the experiment file the setting is written to,
+///         counted from 1. Its runs' seeds are derived from it, see
+///         derive_seed, settings run on their own use 0
+///     parameter: Number of the current setting, counted from 1
+///         across every experiment file of the sweep
+///     total_parameters: Number of settings in the whole sweep
+#[derive(Clone, Copy)]
+struct SweepPosition {
+    sweep: usize,
+    parameter: usize,
+    total_parameters: usize,
+}
+
+impl SweepPosition {
+    /// Writes a "Parameter X of N, run Y of M" progress line
+    /// run: Number of the current run of the setting, counted from 1
+    fn report<W: Write>(&self, run: i64, number_of_runs: i64, out: &mut W) -> io::Result<()> {
+        writeln!(out, "Parameter {} of {}, run {} of {}", self.parameter, self.total_parameters, run, number_of_runs)
+    }
+}
+
+/// Runs the algorithm number_of_runs times with the given parameters,
+/// writing each run's results to the csv at path.
+/// Each written run is recorded in the csv's progress file, and runs
+/// already recorded there are skipped, so an interrupted sweep resumes
+/// where it stopped. A resumed run gets the same seed it would have had.
+/// Callers clear the progress once the csv is complete, see clear_progress.
+/// With the parquet feature, a resumed setting's parquet file only holds
+/// the runs of the resumed call
+/// swept: Name of the parameter varied by the experiment, if any, its
+///     value is written to the Swept_Value column
+/// position: Position of the setting within a sweep, if any, reported
+///     before each run
+/// runner: Runs the ACO once with the given parameters and seed, see run
+/// Returns true if every run of the setting is written to the csv, false
+/// if a run or its write failed and the setting must be resumed
+fn run_experiment<F>(parameters: &HashMap<String, Parameter>, path:&str, number_of_runs: i64, parameter_run: usize, swept: Option<&str>, position: Option<SweepPosition>, runner: &F) -> bool
+where
+    F: Fn((f64, f64, f64, f64, i64, i64), Option<u64>) -> Result<RunResults, GraphError>,
+{
+    let swept_value = swept_value(parameters, swept);
+    let completed = completed_runs(path);
+    #[cfg(feature = "parquet")]
+    let mut rows: Vec<parquet_output::ResultRow> = Vec::new();
+    let mut finished = true;
+    for run_number in 1..=number_of_runs {
         let params: (f64, f64, f64, f64, i64, i64) = Parameter::extract_parameters(parameters);
-        let results: HashMap<String, String> = run(params);
-        match write_to_csv(path, params, results, parameter_run) {
-            Ok(_) => println!("Results written"),
-            Err(e) => println!("{}", e),
+        let key = progress_key(params, parameter_run, run_number);
+        if completed.contains(&key) {
+            continue;
+        }
+        if let Some(position) = position {
+            // Progress is informational, a failed write should not stop the sweep
+            let _ = position.report(run_number, number_of_runs, &mut io::stdout());
+        }
+        let sweep = position.map_or(0, |position| position.sweep);
+        let seed = BASE_SEED.map(|base_seed| algorithm::derive_seed(base_seed, sweep, parameter_run, run_number as usize));
+        let results: RunResults = match runner(params, seed) {
+            Ok(results) => results,
+            Err(e) => {
+                println!("{}", e);
+                return false;
+            }
+        };
+        #[cfg(feature = "parquet")]
+        rows.push(result_row(params, &results, parameter_run, &swept_value));
+        match write_to_csv(path, CSV_LAYOUT, params, &results, parameter_run, &swept_value) {
+            Ok(_) => {
+                println!("Results written");
+                if let Err(e) = record_progress(path, &key) {
+                    println!("Unable to record progress: {}", e);
+                }
+                // Only runs in the csv get a solution, so a resumed run is not written twice
+                if let Some(solutions) = SOLUTIONS_FILE.map(|file_name| Path::new(path).with_file_name(file_name)) {
+                    if let Err(e) = write_solution(&solutions, params, &results, parameter_run) {
+                        println!("Unable to write the solution: {}", e);
+                    }
+                }
+            }
+            Err(e) => {
+                println!("{}", e);
+                finished = false;
+            }
+        }
+    }
+    // Parquet files cannot be appended to, so each parameter setting gets its own file
+    #[cfg(feature = "parquet")]
+    {
+        let parquet_path = Path::new(path).with_extension(format!("{}.parquet", parameter_run));
+        if let Err(e) = parquet_output::write_results(&parquet_path, &rows) {
+            println!("{}", e);
         }
     }
+    finished
 }
 
-/// Given params, runs the ACO algorithm and returns the results as a hashmap of string : string
+/// Runs a single parameter setting outside of a sweep, as the DEFAULT
+/// and CUSTOM modes do, clearing the csv's progress only if every run
+/// finished so an interrupted setting resumes instead of duplicating rows
+/// runner: Runs the ACO once with the given parameters and seed, see run
+/// Returns true if every run was written, see run_experiment
+fn run_setting<F>(parameters: &HashMap<String, Parameter>, path: &str, number_of_runs: i64, runner: &F) -> bool
+where
+    F: Fn((f64, f64, f64, f64, i64, i64), Option<u64>) -> Result<RunResults, GraphError>,
+{
+    let finished = run_experiment(parameters, path, number_of_runs, 1, None, None, runner);
+    if finished {
+        clear_progress(path);
+    }
+    finished
+}
+
+/// Returns the path of the progress file of the csv at path, which
+/// lists the runs already written to the csv, one key per line
+fn progress_path(path: &str) -> String {
+    format!("{}.progress", path)
+}
+
+/// Returns the key a run is recorded under in a progress file, its
+/// parameters, parameter setting and run number
+fn progress_key(params: (f64, f64, f64, f64, i64, i64), parameter_run: usize, run_number: i64) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{}",
+        params.0, params.1, params.2, params.3, params.4, params.5, parameter_run, run_number
+    )
+}
+
+/// Returns the keys of the runs recorded in the csv's progress file,
+/// none if there is no progress file
+fn completed_runs(path: &str) -> HashSet<String> {
+    fs::read_to_string(progress_path(path))
+        .map(|progress| progress.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Adds a run to the csv's progress file, creating the file if needed
+fn record_progress(path: &str, key: &str) -> io::Result<()> {
+    let _guard = CSV_WRITE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut file = OpenOptions::new().append(true).create(true).open(progress_path(path))?;
+    writeln!(file, "{}", key)
+}
+
+/// Removes the csv's progress file once all its runs are written, so
+/// a later sweep into the same csv runs in full again
+fn clear_progress(path: &str) {
+    // The progress file is only missing if no run was written
+    let _ = fs::remove_file(progress_path(path));
+}
+
+/// Builds the typed Parquet row of a run, with the same values as its csv row
+#[cfg(feature = "parquet")]
+fn result_row(params: (f64, f64, f64, f64, i64, i64), results: &RunResults, parameter_run: usize, swept_value: &str) -> parquet_output::ResultRow {
+    parquet_output::ResultRow {
+        run_id: run_id().to_string(),
+        parameter: parameter_run as i64,
+        swept_value: swept_value.to_string(),
+        alpha: params.0,
+        beta: params.1,
+        evaporation_rate: params.2,
+        p_rate: params.3,
+        number_of_ants: params.4,
+        fitness_evals: params.5,
+        initial_fitness: results.initial_score,
+        initial_avg: results.initial_avg,
+        top_fitness: results.final_score,
+        final_avg: results.final_avg,
+    }
+}
+
+/// Given params, runs the ACO algorithm and returns the results
 /// params in the order of 
 /// (
 ///  f64: alpha,
@@ -149,31 +786,123 @@ fn run_experiment(parameters: &HashMap<String, Parameter>, path:&str, number_of_
 ///  i64: num_of_ants,
 ///  i64: fitness_evals
 /// )
-fn run(params: (f64, f64, f64, f64, i64, i64)) -> HashMap<String, String> {
-    algorithm::run(
+/// verbose: True if the progress and extra information are printed
+/// seed: Seed of the run, random if None
+/// problem: Path of the problem file, the default problem file if None
+fn run(params: (f64, f64, f64, f64, i64, i64), verbose: bool, seed: Option<u64>, problem: Option<&Path>) -> Result<RunResults, GraphError> {
+    let problem_file = problem.map(Path::to_path_buf);
+    let results = algorithm::run(
         params.0,
         params.1,
         params.2,
         params.4,
         params.5,
         params.3,        
-        true
-    )
+        &algorithm::RunOptions { verbose, seed, optimum: KNOWN_OPTIMUM, normalization: SCORE_NORMALIZATION, problem_file, ..Default::default() },
+    )?;
+    for warning in results.warnings.iter() {
+        println!("Warning: {}", warning);
+    }
+    Ok(results)
 } 
 
-// Writes ACO's results to the csv
-fn write_to_csv(path: &str, params: (f64, f64, f64, f64, i64, i64), results: HashMap<String, String>, parameter_run: usize) -> Result<(), Box<dyn Error>> {
-    init_csv(path)?;
+/// Validates every problem file in dir, writing a verdict per file
+/// Returns true if every file is valid
+fn validate_problem_files<W: Write>(dir: &Path, mode: ParseMode, out: &mut W) -> bool {
+    let verdicts = match graph::validate_problem_files(dir, mode) {
+        Ok(verdicts) => verdicts,
+        Err(e) => {
+            let _ = writeln!(out, "{}", e);
+            return false;
+        }
+    };
+    let mut all_valid = true;
+    for (path, verdict) in verdicts {
+        let _ = match verdict {
+            Ok(()) => writeln!(out, "OK      {}", path.display()),
+            Err(e) => {
+                all_valid = false;
+                writeln!(out, "FAILED  {}: {}", path.display(), e)
+            }
+        };
+    }
+    all_valid
+}
+
+/// Returns the value of the swept parameter as written to the csv,
+/// or an empty string if no parameter is swept
+fn swept_value(parameters: &HashMap<String, Parameter>, swept: Option<&str>) -> String {
+    swept
+        .and_then(|name| parameters.get(name))
+        .map(|parameter| parameter.to_string())
+        .unwrap_or_default()
+}
+
+// Writes ACO's results to the csv, one value per column in the given order
+fn write_to_csv(path: &str, layout: CsvLayout, params: (f64, f64, f64, f64, i64, i64), results: &RunResults, parameter_run: usize, swept_value: &str) -> Result<(), Box<dyn Error>> {
+    // A panicked writer leaves no partial state behind the lock, so a poisoned lock is still usable
+    let _guard = CSV_WRITE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    init_csv(path, layout)?;
     
-    // Open the file in append mode as to note write over previous data
-    let file = OpenOptions::new().append(true).open(path)?;
+    // Open the file in append mode as to note write over previous data,
+    // creating it if this is the first write
+    let file = OpenOptions::new().append(true).create(true).open(path)?;
     let mut wtr = csv::Writer::from_writer(file);
 
-    let difference = results.get("final_score").unwrap().parse::<f64>()? - results.get("initial_score").unwrap().parse::<f64>()?;
-    let avg_difference = results.get("final_avg").unwrap().parse::<f64>()? - results.get("initial_avg").unwrap().parse::<f64>()?;
-    
+    let difference = results.final_score - results.initial_score;
+    let avg_difference = results.final_avg - results.initial_avg;
+    // Optional results are left empty when the run did not report them
+    let optional = |value: Option<f64>| value.map_or_else(String::new, |value| value.to_string());
+
     // Write record
-    wtr.write_record(&[
+    wtr.write_record(layout.columns.iter().map(|column| match column {
+        Column::RunId => run_id().to_string(),
+        Column::Parameter => parameter_run.to_string(),
+        Column::SweptValue => swept_value.to_string(),
+        Column::Alpha => params.0.to_string(),
+        Column::Beta => params.1.to_string(),
+        Column::EvaporationRate => params.2.to_string(),
+        Column::PRate => params.3.to_string(),
+        Column::NumberOfAnts => params.4.to_string(),
+        Column::FitnessEvals => params.5.to_string(),
+        Column::InitialFitness => results.initial_score.to_string(),
+        Column::InitialAvg => results.initial_avg.to_string(),
+        Column::TopFitness => results.final_score.to_string(),
+        Column::FinalAvg => results.final_avg.to_string(),
+        Column::BestFitnessDifference => difference.trunc().to_string(),
+        Column::AvgDifference => avg_difference.trunc().to_string(),
+        Column::OptimalityGap => optional(results.optimality_gap),
+        Column::LastImprovement => results.last_improvement.to_string(),
+        Column::ImprovedOverGreedy => results.improved_over_greedy.to_string(),
+        Column::UnusedCapacity => results.unused_capacity.to_string(),
+        Column::SlackAdmitsBag => results.slack_admits_bag.to_string(),
+        Column::Seed => results.seed.to_string(),
+        Column::NormalizedScore => optional(results.normalized_score),
+    }))?;
+    
+    // Flush buffer and return
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Appends the best tour of a run to the solutions csv at path, writing
+/// the header first if the file is new. See SOLUTIONS_FILE
+/// results: Results of the run, see algorithm::run
+/// parameter_run: Number of the run's parameter setting
+fn write_solution(path: &Path, params: (f64, f64, f64, f64, i64, i64), results: &RunResults, parameter_run: usize) -> Result<(), Box<dyn Error>> {
+    let _guard = CSV_WRITE.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let has_data = fs::metadata(path).map(|metadata| metadata.len() > 0).unwrap_or(false);
+    let file = OpenOptions::new().append(true).create(true).open(path)?;
+    let mut wtr = csv::Writer::from_writer(file);
+    if !has_data {
+        wtr.write_record([
+            "Run_Id", "Parameter", "Alpha", "Beta", "Evaporation_Rate", "p_rate", "Number_Of_Ants", "Fitness_Evals",
+            "Bags", "Cost", "Weight",
+        ])?;
+    }
+    let bags: Vec<String> = results.best_tour.iter().map(|bag| bag.to_string()).collect();
+    wtr.write_record([
+        run_id().to_string(),
         parameter_run.to_string(),
         params.0.to_string(),
         params.1.to_string(),
@@ -181,45 +910,88 @@ fn write_to_csv(path: &str, params: (f64, f64, f64, f64, i64, i64), results: Has
         params.3.to_string(),
         params.4.to_string(),
         params.5.to_string(),
-        results.get("initial_score").unwrap().to_string(),
-        results.get("initial_avg").unwrap().to_string(),
-        results.get("final_score").unwrap().to_string(),
-        results.get("final_avg").unwrap().to_string(),
-        difference.trunc().to_string(),
-        avg_difference.trunc().to_string(),
+        bags.join(" "),
+        results.final_score.to_string(),
+        results.best_weight.to_string(),
     ])?;
-    
-    // Flush buffer and return
     wtr.flush()?;
     Ok(())
 }
 
-/// Writes the headers to the csv, wiping any previous data
-fn init_csv(path: &str) -> Result<(), Box<dyn Error>> {
-    // Writes the headers to the csv files
-    // Unsafe due to the modification of a static, mutable variables - CSV_INITILIZED
-    unsafe {
-        if !CSV_INITILIZED {
-            let mut wtr = csv::Writer::from_path(path)?;
-            wtr.write_record(
-            &[
-                "Parameter",
-                "Alpha", 
-                "Beta", 
-                "Evaporation_Rate",
-                "p_rate",
-                "Number_Of_Ants", 
-                "Fitness_Evals", 
-                "Initial_fitness", 
-                "Initial_avg",
-                "Top_Fitness", 
-                "Final_avg",
-                "Best_Fitness_Difference",
-                "Avg_Difference",
-            ])?;
-            wtr.flush()?;
-            CSV_INITILIZED = true; 
+/// Returns the metadata comment line of a new csv, recording the crate
+/// version, the base seed and the creation time in seconds since the
+/// epoch. Without a base seed, the seed is recorded as none
+fn metadata_comment() -> Result<String, Box<dyn Error>> {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let seed = BASE_SEED.map_or("none".to_string(), |seed| seed.to_string());
+    Ok(format!(
+        "# version={} seed={} timestamp={} run_id={}\n",
+        env!("CARGO_PKG_VERSION"), seed, timestamp, run_id()
+    ))
+}
+
+/// Returns the id of this invocation, generated on first use
+fn run_id() -> &'static str {
+    RUN_ID.get_or_init(generate_run_id)
+}
+
+/// Generates a new run id from the current time, the process id
+/// and a counter, formatted as hex
+fn generate_run_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_nanos()).unwrap_or(0);
+    format!("{:x}-{:x}-{:x}", nanos, std::process::id(), RUN_ID_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Returns OUTPUT_DIR resolved against the base directory
+fn output_dir() -> String {
+    graph::resolve_path(&graph::base_dir(), Path::new(OUTPUT_DIR)).to_string_lossy().into_owned()
+}
+
+/// Builds the path of a results file inside the output directory,
+/// creating the directory if it does not exist yet.
+/// output_dir: Directory the file is written to
+/// file_name: Name of the file, with .csv as the suffix
+/// timestamp: If true, the current time in milliseconds is appended to
+///     the file name so each experiment gets its own file
+fn output_path(output_dir: &str, file_name: &str, timestamp: bool) -> Result<String, Box<dyn Error>> {
+    fs::create_dir_all(output_dir)?;
+    let file_name = if timestamp {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        match file_name.rsplit_once('.') {
+            Some((stem, extension)) => format!("{}_{}.{}", stem, millis, extension),
+            None => format!("{}_{}", file_name, millis),
         }
+    } else {
+        file_name.to_string()
+    };
+    Ok(Path::new(output_dir).join(file_name).to_string_lossy().into_owned())
+}
+
+/// Writes the headers of the layout's columns to the csv if it is new,
+/// preceded by the metadata comment if the layout asks for it. A file
+/// that already has data is left as it is so rows are appended after
+/// its existing header
+/// Returns an error if the existing header does not match the layout's
+/// columns, as the appended rows would not line up with it
+fn init_csv(path: &str, layout: CsvLayout) -> Result<(), Box<dyn Error>> {
+    let has_data = fs::metadata(path).map(|metadata| metadata.len() > 0).unwrap_or(false);
+    if has_data {
+        let mut rdr = csv::ReaderBuilder::new().comment(Some(b'#')).from_path(path)?;
+        let header: Vec<&str> = layout.columns.iter().map(Column::header).collect();
+        if rdr.headers()?.iter().ne(header.iter().copied()) {
+            return Err(format!(
+                "{} has the header {:?}, which does not match the columns {:?}",
+                path, rdr.headers()?.iter().collect::<Vec<&str>>(), header
+            ).into());
+        }
+    } else {
+        if layout.metadata {
+            fs::write(path, metadata_comment()?)?;
+        }
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        let mut wtr = csv::Writer::from_writer(file);
+        wtr.write_record(layout.columns.iter().map(Column::header))?;
+        wtr.flush()?;
     }
     Ok(())
 }
@@ -277,3 +1049,422 @@ where
         .interact()
         .unwrap().parse::<T>().unwrap()
 }
+
+/// Prompts for a path that may be left empty
+/// Returns None if nothing was entered
+fn optional_path_input(prompt: &str) -> Option<PathBuf> {
+    let input: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .allow_empty(true)
+        .interact_text()
+        .unwrap();
+    let input = input.trim();
+    (!input.is_empty()).then(|| PathBuf::from(input))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Every column, without the metadata comment
+    const ALL_COLUMNS: CsvLayout = CsvLayout { columns: &Column::ALL, metadata: false };
+
+    /// Builds a set of results as returned by algorithm::run
+    fn sample_results() -> RunResults {
+        RunResults { initial_score: 100.0, initial_avg: 80.0, final_score: 150.0, final_avg: 120.0, ..Default::default() }
+    }
+
+    /// Tests the output directory is created and a timestamped file is written into it
+    #[test]
+    fn output_directory() {
+        let root = std::env::temp_dir().join(format!("aco_output_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let output_dir = root.join("nested");
+        let output_dir = output_dir.to_str().unwrap();
+
+        let path = output_path(output_dir, "results.csv", true).unwrap();
+        assert!(Path::new(output_dir).is_dir());
+        let file_name = Path::new(&path).file_name().unwrap().to_str().unwrap();
+        assert!(file_name.starts_with("results_") && file_name.ends_with(".csv"));
+        assert_ne!(file_name, "results.csv");
+
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 10, 100), &sample_results(), 1, "").unwrap();
+        assert!(Path::new(&path).is_file());
+        assert_eq!(output_path(output_dir, "results.csv", false).unwrap(), Path::new(output_dir).join("results.csv").to_string_lossy());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Tests every row of an invocation shares its run id and new ids are distinct
+    #[test]
+    fn run_id_column() {
+        let output_dir = std::env::temp_dir().join(format!("aco_run_id_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 10, 100), &sample_results(), 1, "").unwrap();
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 20, 100), &sample_results(), 2, "").unwrap();
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let ids: Vec<String> = rdr.records().map(|record| record.unwrap()[0].to_string()).collect();
+        assert_eq!(ids, vec![run_id().to_string(), run_id().to_string()]);
+        assert_ne!(generate_run_id(), generate_run_id());
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests the swept value column holds the value of the parameter varied by the experiment
+    #[test]
+    fn swept_value_column() {
+        let output_dir = std::env::temp_dir().join(format!("aco_swept_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        let experiment_params = ResearchSet::set_evaporation_params(vec![0.1, 0.25]);
+        for (parameter_run, parameters) in experiment_params.iter().enumerate() {
+            let params = Parameter::extract_parameters(parameters);
+            let swept = swept_value(parameters, Some("evaporation_rate"));
+            write_to_csv(&path, ALL_COLUMNS, params, &sample_results(), parameter_run + 1, &swept).unwrap();
+        }
+        assert_eq!(swept_value(&experiment_params[0], None), "");
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|record| record.unwrap()).collect();
+        assert_eq!(rows.iter().map(|row| row[2].to_string()).collect::<Vec<String>>(), vec!["0.1", "0.25"]);
+        // The swept value matches the evaporation rate column
+        assert!(rows.iter().all(|row| row[2] == row[5]));
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests the sweep progress lines count settings across every experiment file
+    #[test]
+    fn sweep_progress() {
+        let sweeps = experiment_sweeps();
+        let mut position = SweepPosition {
+            sweep: 1,
+            parameter: 0,
+            total_parameters: sweeps.iter().map(|(_, _, experiment_params)| experiment_params.len()).sum(),
+        };
+        let mut out: Vec<u8> = Vec::new();
+        for (_, _, experiment_params) in sweeps {
+            for _ in experiment_params {
+                position.parameter += 1;
+                for run_number in 1..=5 {
+                    position.report(run_number, 5, &mut out).unwrap();
+                }
+            }
+        }
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 24 * 5);
+        assert_eq!(lines[0], "Parameter 1 of 24, run 1 of 5");
+        assert_eq!(lines[46], "Parameter 10 of 24, run 2 of 5");
+        assert_eq!(lines[119], "Parameter 24 of 24, run 5 of 5");
+    }
+
+    /// Tests a new csv gets the header and an existing one keeps its header with rows appended
+    #[test]
+    fn csv_header() {
+        let output_dir = std::env::temp_dir().join(format!("aco_header_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 10, 100), &sample_results(), 1, "").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("Run_Id,Parameter,Swept_Value,"));
+        assert_eq!(contents.lines().count(), 2);
+
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 20, 100), &sample_results(), 2, "").unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.matches("Run_Id").count(), 1);
+        assert_eq!(contents.lines().count(), 3);
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let ants: Vec<String> = rdr.records().map(|record| record.unwrap()[7].to_string()).collect();
+        assert_eq!(ants, vec!["10", "20"]);
+
+        // Rows of another layout are not appended under the existing header
+        let layout = CsvLayout { columns: &[Column::NumberOfAnts], metadata: false };
+        let error = write_to_csv(&path, layout, (1.0, 2.0, 0.1, 1.0, 30, 100), &sample_results(), 3, "").unwrap_err();
+        assert!(error.to_string().contains("does not match"));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests the optimality gap column is filled only when the results have a gap
+    #[test]
+    fn optimality_gap_column() {
+        let output_dir = std::env::temp_dir().join(format!("aco_gap_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        let mut results = sample_results();
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 10, 100), &results, 1, "").unwrap();
+        results.optimality_gap = algorithm::optimality_gap(200.0, 150.0);
+        write_to_csv(&path, ALL_COLUMNS, (1.0, 2.0, 0.1, 1.0, 10, 100), &results, 1, "").unwrap();
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(&rdr.headers().unwrap()[15], "Optimality_Gap");
+        let gaps: Vec<String> = rdr.records().map(|record| record.unwrap()[15].to_string()).collect();
+        assert_eq!(gaps, vec!["", "0.25"]);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests a custom column subset writes a matching header and row
+    #[test]
+    fn custom_columns() {
+        let output_dir = std::env::temp_dir().join(format!("aco_columns_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        let columns = [Column::NumberOfAnts, Column::TopFitness, Column::BestFitnessDifference];
+        write_to_csv(&path, CsvLayout { columns: &columns, metadata: false }, (1.0, 2.0, 0.1, 1.0, 10, 100), &sample_results(), 1, "").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Number_Of_Ants,Top_Fitness,Best_Fitness_Difference\n10,150,50\n");
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests the metadata comment comes before the header and records the version, seed and time
+    #[test]
+    fn metadata_comment_line() {
+        let output_dir = std::env::temp_dir().join(format!("aco_metadata_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", true).unwrap();
+        let layout = CsvLayout { columns: &[Column::NumberOfAnts], metadata: true };
+        write_to_csv(&path, layout, (1.0, 2.0, 0.1, 1.0, 10, 100), &sample_results(), 1, "").unwrap();
+        write_to_csv(&path, layout, (1.0, 2.0, 0.1, 1.0, 20, 100), &sample_results(), 1, "").unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[0].starts_with(&format!("# version={} seed=none timestamp=", env!("CARGO_PKG_VERSION"))));
+        assert!(lines[0].ends_with(&format!("run_id={}", run_id())));
+        assert_eq!(&lines[1..], ["Number_Of_Ants", "10", "20"]);
+
+        let mut rdr = csv::ReaderBuilder::new().comment(Some(b'#')).from_path(&path).unwrap();
+        assert_eq!(rdr.records().count(), 2);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests two piped job lines produce two outputs and a status line each
+    #[test]
+    fn batch_jobs() {
+        let output_dir = std::env::temp_dir().join(format!("aco_batch_{}", std::process::id()));
+        fs::create_dir_all(&output_dir).unwrap();
+        let first = output_dir.join("first.csv");
+        let second = output_dir.join("second.csv");
+        let input = format!(
+            "{{\"alpha\": 1.0, \"beta\": 2.0, \"evaporation_rate\": 0.1, \"p_rate\": 1.0, \"num_of_ants\": 10, \"fitness_evals\": 100, \"output\": {:?}}}\n\n\
+             {{\"alpha\": 2.0, \"beta\": 2.0, \"evaporation_rate\": 0.2, \"p_rate\": 1.0, \"num_of_ants\": 20, \"fitness_evals\": 100, \"output\": {:?}, \"runs\": 2}}\n",
+            first, second
+        );
+        let mut out: Vec<u8> = Vec::new();
+        let failed = run_batch(input.as_bytes(), &mut out, 2, |_| Ok(sample_results()));
+        assert_eq!(failed, 0);
+        assert_eq!(String::from_utf8(out).unwrap().lines().filter(|line| line.contains("done")).count(), 2);
+
+        let ants = |path: &Path| -> Vec<String> {
+            let mut rdr = csv::Reader::from_path(path).unwrap();
+            rdr.records().map(|record| record.unwrap()[7].to_string()).collect()
+        };
+        assert_eq!(ants(&first), vec!["10"]);
+        assert_eq!(ants(&second), vec!["20", "20"]);
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        assert_eq!(batch_workers(&args(&["--batch", "--jobs", "4"])), Ok(Some(4)));
+        assert_eq!(batch_workers(&args(&["--batch"])), Ok(Some(1)));
+        assert_eq!(batch_workers(&[]), Ok(None));
+        assert!(batch_workers(&args(&["--batch", "--jobs", "four"])).unwrap_err().starts_with("Usage"));
+        assert!(batch_workers(&args(&["--batch", "--jobs"])).is_err());
+
+        // A line that is not UTF-8 stops the reading and counts as a failure
+        let mut input = format!(
+            "{{\"alpha\": 1.0, \"beta\": 2.0, \"evaporation_rate\": 0.1, \"p_rate\": 1.0, \"num_of_ants\": 30, \"fitness_evals\": 100, \"output\": {:?}}}\n",
+            first
+        ).into_bytes();
+        input.extend_from_slice(b"\xff\xfe\n");
+        let mut out: Vec<u8> = Vec::new();
+        assert_eq!(run_batch(input.as_slice(), &mut out, 1, |_| Ok(sample_results())), 1);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("Unable to read line 2"));
+        assert!(out.contains("Job 1 done"));
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests every sweep's csv gets all of its rows when the sweeps run concurrently
+    #[test]
+    fn concurrent_sweeps() {
+        let output_dir = std::env::temp_dir().join(format!("aco_sweeps_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        let sweeps: Vec<Sweep> = vec![
+            ("ants.csv", "num_of_ants", ResearchSet::set_ant_number_params(vec![2, 5])),
+            ("evaporation.csv", "evaporation_rate", ResearchSet::set_evaporation_params(vec![0.1, 0.2, 0.3])),
+            ("p_rate.csv", "p_rate", ResearchSet::set_p_rate_params(vec![0.5])),
+        ];
+        let output = output_dir.to_string_lossy().into_owned();
+        run_sweeps(sweeps, &output, 2, 4, MAX_OUTPUT_FILES, |_, _| Ok(sample_results())).unwrap();
+
+        let rows = |file_name: &str| {
+            let mut rdr = csv::Reader::from_path(output_dir.join(file_name)).unwrap();
+            let mut parameters: Vec<String> = rdr.records().map(|record| record.unwrap()[1].to_string()).collect();
+            parameters.sort();
+            parameters
+        };
+        assert_eq!(rows("ants.csv"), vec!["1", "1", "2", "2"]);
+        assert_eq!(rows("evaporation.csv"), vec!["1", "1", "2", "2", "3", "3"]);
+        assert_eq!(rows("p_rate.csv"), vec!["1", "1"]);
+        let has_progress = |file_name: &str| Path::new(&progress_path(output_dir.join(file_name).to_str().unwrap())).exists();
+        assert!(!has_progress("ants.csv") && !has_progress("evaporation.csv"));
+
+        // A failed setting keeps its csv's progress, the finished csvs are cleared
+        let sweeps: Vec<Sweep> = vec![
+            ("ants.csv", "num_of_ants", ResearchSet::set_ant_number_params(vec![2])),
+            ("evaporation.csv", "evaporation_rate", ResearchSet::set_evaporation_params(vec![0.1, 0.2])),
+        ];
+        run_sweeps(sweeps, &output, 2, 2, MAX_OUTPUT_FILES, |params, _| {
+            if params.2 == 0.2 { Err(GraphError::EmptyProblem) } else { Ok(sample_results()) }
+        }).unwrap();
+        assert!(!has_progress("ants.csv"));
+        assert!(has_progress("evaporation.csv"));
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests a recorded run is reproduced from its row and a mismatched score is reported
+    #[test]
+    fn replay_row() {
+        let output_dir = std::env::temp_dir().join(format!("aco_replay_{}", std::process::id()));
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", false).unwrap();
+        let params = (1.0, 2.0, 0.1, 1.0, 10, 100);
+        let results = run(params, false, None, None).unwrap();
+        write_to_csv(&path, CSV_LAYOUT, params, &results, 1, "").unwrap();
+
+        let replayed = replay(Path::new(&path), 0, |params, seed| run(params, false, seed, None)).unwrap();
+        assert_eq!(replayed.final_score, results.final_score);
+        assert_eq!(replayed.best_tour, results.best_tour);
+
+        // A run that does not follow its seed is caught
+        let other = RunResults { final_score: -1.0, ..sample_results() };
+        assert!(replay(Path::new(&path), 0, |_, _| Ok(other.clone())).is_err());
+        assert!(replay(Path::new(&path), 1, |params, seed| run(params, false, seed, None)).is_err());
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests the printed configuration reflects the arguments and the base directory variable over the defaults
+    #[test]
+    fn print_config() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+        let config = effective_config(&args(&["--print-config"]), None);
+        assert!(config.contains(&format!("base_dir: {} (from crate directory)\n", env!("CARGO_MANIFEST_DIR"))));
+        assert!(config.contains("batch: off\n"));
+        assert!(config.contains("seed: none\n"));
+
+        let base = std::env::temp_dir().join("aco_config");
+        let config = effective_config(&args(&["--batch", "--jobs", "3", "--print-config"]), Some(base.clone().into_os_string()));
+        assert!(config.contains(&format!("base_dir: {} (from ACO_BASE_DIR)\n", base.display())));
+        assert!(config.contains(&format!("problem_file: {}\n", base.join("src").join("BankProblem.txt").display())));
+        assert!(config.contains(&format!("output_dir: {}\n", base.join(OUTPUT_DIR).display())));
+        assert!(config.contains("batch: 3 workers\n"));
+    }
+
+    /// Tests sweeps writing to more files than the cap are refused before anything runs
+    #[test]
+    fn output_file_cap() {
+        let output_dir = std::env::temp_dir().join(format!("aco_file_cap_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        let output = output_dir.to_string_lossy().into_owned();
+        let sweeps = || -> Vec<Sweep> { vec![
+            ("ants.csv", "num_of_ants", ResearchSet::set_ant_number_params(vec![2])),
+            ("evaporation.csv", "evaporation_rate", ResearchSet::set_evaporation_params(vec![0.1])),
+            ("ants.csv", "num_of_ants", ResearchSet::set_ant_number_params(vec![5])),
+        ] };
+        let runs = std::sync::atomic::AtomicUsize::new(0);
+        let runner = |_, _| {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Ok(sample_results())
+        };
+        let err = run_sweeps(sweeps(), &output, 1, 1, 1, runner).unwrap_err();
+        assert!(err.to_string().contains("2 results files"));
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+        assert!(!output_dir.exists());
+
+        run_sweeps(sweeps(), &output, 1, 1, 2, runner).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests an interrupted setting resumes with only the runs it has not written yet
+    #[test]
+    fn resume_sweep() {
+        let output_dir = std::env::temp_dir().join(format!("aco_resume_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", false).unwrap();
+        let parameters = ResearchSet::set_ant_number_params(vec![5]).remove(0);
+
+        // The sweep crashes on its third run
+        let calls = std::cell::Cell::new(0);
+        let crashing = |_, _| {
+            calls.set(calls.get() + 1);
+            if calls.get() == 3 { Err(GraphError::EmptyProblem) } else { Ok(sample_results()) }
+        };
+        assert!(!run_experiment(&parameters, &path, 4, 1, None, None, &crashing));
+        assert_eq!(completed_runs(&path).len(), 2);
+
+        let resumed = std::cell::Cell::new(0);
+        assert!(run_experiment(&parameters, &path, 4, 1, None, None, &|_, _| {
+            resumed.set(resumed.get() + 1);
+            Ok(sample_results())
+        }));
+        assert_eq!(resumed.get(), 2);
+        assert_eq!(csv::Reader::from_path(&path).unwrap().records().count(), 4);
+
+        // Once cleared, the setting runs in full again
+        clear_progress(&path);
+        assert!(!Path::new(&progress_path(&path)).exists());
+        run_experiment(&parameters, &path, 4, 1, None, None, &|_, _| {
+            resumed.set(resumed.get() + 1);
+            Ok(sample_results())
+        });
+        assert_eq!(resumed.get(), 6);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests a single setting keeps its progress when a run fails and clears it once every run is written
+    #[test]
+    fn single_setting_progress() {
+        let output_dir = std::env::temp_dir().join(format!("aco_single_setting_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        let path = output_path(output_dir.to_str().unwrap(), "results.csv", false).unwrap();
+        let parameters = ResearchSet::set_ant_number_params(vec![5]).remove(0);
+
+        let calls = std::cell::Cell::new(0);
+        let crashing = |_, _| {
+            calls.set(calls.get() + 1);
+            if calls.get() == 2 { Err(GraphError::EmptyProblem) } else { Ok(sample_results()) }
+        };
+        assert!(!run_setting(&parameters, &path, 3, &crashing));
+        assert_eq!(completed_runs(&path).len(), 1);
+
+        // The resumed setting only writes the missing runs, then clears its progress
+        assert!(run_setting(&parameters, &path, 3, &|_, _| Ok(sample_results())));
+        assert!(!Path::new(&progress_path(&path)).exists());
+        assert_eq!(csv::Reader::from_path(&path).unwrap().records().count(), 3);
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    /// Tests one solution line is written per run, holding the bags of its best tour
+    #[test]
+    fn solutions_file() {
+        let output_dir = std::env::temp_dir().join(format!("aco_solutions_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&output_dir);
+        fs::create_dir_all(&output_dir).unwrap();
+        let path = output_dir.join("solutions.csv");
+        let params = (1.0, 2.0, 0.1, 1.0, 10, 100);
+        let runs: Vec<RunResults> = (0..2).map(|seed| run(params, false, Some(seed), None).unwrap()).collect();
+        for results in &runs {
+            write_solution(&path, params, results, 3).unwrap();
+        }
+
+        let graph = graph::Graph::from_file(&graph::problem_file(&graph::base_dir()), 1.0).unwrap();
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        assert_eq!(rdr.headers().unwrap().get(8), Some("Bags"));
+        let records: Vec<csv::StringRecord> = rdr.records().map(|record| record.unwrap()).collect();
+        assert_eq!(records.len(), 2);
+        for (record, results) in records.iter().zip(&runs) {
+            assert_eq!(&record[0], run_id());
+            assert_eq!(&record[1], "3");
+            let bags: Vec<usize> = record[8]
+                .split(' ')
+                .map(|number| graph.index_of(number.parse().unwrap()).unwrap())
+                .collect();
+            let (cost, weight) = graph.evaluate_tour(&bags).unwrap();
+            assert_eq!(cost, results.final_score);
+            assert_eq!(record[9].parse::<f64>().unwrap(), results.final_score);
+            assert_eq!(record[10].parse::<f64>().unwrap(), weight);
+        }
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
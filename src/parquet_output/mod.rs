@@ -0,0 +1,146 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use parquet::arrow::ArrowWriter;
+use crate::ant::IterationResult;
+
+/// One run of an experiment, with the same fields as a row of the
+/// results csv but typed, see write_to_csv in main
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRow {
+    pub run_id: String,
+    pub parameter: i64,
+    pub swept_value: String,
+    pub alpha: f64,
+    pub beta: f64,
+    pub evaporation_rate: f64,
+    pub p_rate: f64,
+    pub number_of_ants: i64,
+    pub fitness_evals: i64,
+    pub initial_fitness: f64,
+    pub initial_avg: f64,
+    pub top_fitness: f64,
+    pub final_avg: f64,
+}
+
+/// Writes the results of many runs to a Parquet file, one row per run.
+/// Columns are named as in the results csv
+pub fn write_results(path: &Path, rows: &[ResultRow]) -> Result<(), Box<dyn Error>> {
+    let strings = |field: fn(&ResultRow) -> &str| -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(rows.iter().map(field)))
+    };
+    let floats = |field: fn(&ResultRow) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(field)))
+    };
+    let integers = |field: fn(&ResultRow) -> i64| -> ArrayRef {
+        Arc::new(Int64Array::from_iter_values(rows.iter().map(field)))
+    };
+    let batch = RecordBatch::try_from_iter(vec![
+        ("Run_Id", strings(|row| &row.run_id)),
+        ("Parameter", integers(|row| row.parameter)),
+        ("Swept_Value", strings(|row| &row.swept_value)),
+        ("Alpha", floats(|row| row.alpha)),
+        ("Beta", floats(|row| row.beta)),
+        ("Evaporation_Rate", floats(|row| row.evaporation_rate)),
+        ("p_rate", floats(|row| row.p_rate)),
+        ("Number_Of_Ants", integers(|row| row.number_of_ants)),
+        ("Fitness_Evals", integers(|row| row.fitness_evals)),
+        ("Initial_fitness", floats(|row| row.initial_fitness)),
+        ("Initial_avg", floats(|row| row.initial_avg)),
+        ("Top_Fitness", floats(|row| row.top_fitness)),
+        ("Final_avg", floats(|row| row.final_avg)),
+    ])?;
+    write_batch(path, &batch)
+}
+
+/// Writes a colony's convergence history to a Parquet file, one row per iteration
+pub fn write_history(path: &Path, history: &[IterationResult]) -> Result<(), Box<dyn Error>> {
+    let batch = RecordBatch::try_from_iter(vec![
+        ("Iteration", Arc::new(Int64Array::from_iter_values(history.iter().map(|point| point.iteration as i64))) as ArrayRef),
+        ("Evaluations", Arc::new(Int64Array::from_iter_values(history.iter().map(|point| point.evaluations)))),
+        ("Iteration_Best", Arc::new(Float64Array::from_iter_values(history.iter().map(|point| point.iteration_best)))),
+        ("Global_Best", Arc::new(Float64Array::from_iter_values(history.iter().map(|point| point.global_best)))),
+        ("Average", Arc::new(Float64Array::from_iter_values(history.iter().map(|point| point.average)))),
+        ("Diversity", Arc::new(Float64Array::from_iter(history.iter().map(|point| point.diversity)))),
+        ("Entropy", Arc::new(Float64Array::from_iter(history.iter().map(|point| point.entropy)))),
+    ])?;
+    write_batch(path, &batch)
+}
+
+/// Writes a record batch to a new Parquet file, replacing any existing file
+fn write_batch(path: &Path, batch: &RecordBatch) -> Result<(), Box<dyn Error>> {
+    let file = File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow_array::Array;
+    use arrow_schema::DataType;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    /// Reads a Parquet file back, the test files are small enough to be a single record batch
+    fn read_back(path: &Path) -> RecordBatch {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+        let mut batches: Vec<RecordBatch> = reader.map(|batch| batch.unwrap()).collect();
+        assert_eq!(batches.len(), 1);
+        batches.remove(0)
+    }
+
+    /// Tests results and history written to Parquet read back with typed columns and the same values
+    #[test]
+    fn parquet_round_trip() {
+        let dir = std::env::temp_dir().join(format!("aco_parquet_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let row = ResultRow {
+            run_id: "abc".to_string(),
+            parameter: 2,
+            swept_value: "0.2".to_string(),
+            alpha: 1.0,
+            beta: 2.0,
+            evaporation_rate: 0.2,
+            p_rate: 1.0,
+            number_of_ants: 20,
+            fitness_evals: 1000,
+            initial_fitness: 3000.5,
+            initial_avg: 2500.0,
+            top_fitness: 4000.0,
+            final_avg: 3500.25,
+        };
+        let path = dir.join("results.parquet");
+        write_results(&path, &[row.clone(), ResultRow { parameter: 3, top_fitness: 4100.0, ..row }]).unwrap();
+
+        let batch = read_back(&path);
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        assert_eq!(schema.fields().len(), 13);
+        assert_eq!(schema.field_with_name("Run_Id").unwrap().data_type(), &DataType::Utf8);
+        assert_eq!(schema.field_with_name("Number_Of_Ants").unwrap().data_type(), &DataType::Int64);
+        assert_eq!(schema.field_with_name("Top_Fitness").unwrap().data_type(), &DataType::Float64);
+        let top_fitness = batch.column_by_name("Top_Fitness").unwrap().as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(top_fitness.values().to_vec(), vec![4000.0, 4100.0]);
+        let run_ids = batch.column_by_name("Run_Id").unwrap().as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(run_ids.value(1), "abc");
+
+        let path = dir.join("history.parquet");
+        let history = vec![
+            IterationResult { iteration: 1, evaluations: 20, iteration_best: 10.0, global_best: 10.0, average: 5.0, diversity: Some(0.5), entropy: Some(2.0) },
+            IterationResult { iteration: 2, evaluations: 40, iteration_best: 8.0, global_best: 10.0, average: 6.0, diversity: None, entropy: None },
+        ];
+        write_history(&path, &history).unwrap();
+        let batch = read_back(&path);
+        let evaluations = batch.column_by_name("Evaluations").unwrap().as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(evaluations.values().to_vec(), vec![20, 40]);
+        assert_eq!(batch.column_by_name("Global_Best").unwrap().len(), 2);
+        // Tour metrics left out of the history are written as nulls
+        assert_eq!(batch.column_by_name("Diversity").unwrap().null_count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
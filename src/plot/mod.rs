@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::path::Path;
+use plotters::prelude::*;
+use crate::ant::IterationResult;
+
+/// Size of the sparkline in pixels, small enough to glance at
+const SPARKLINE_SIZE: (u32, u32) = (400, 120);
+
+/// Draws the global best of a convergence history against the fitness
+/// evaluations as an SVG sparkline. There are no axes or labels, the
+/// curve only shows whether the run converged or plateaued
+/// path: SVG file to write, replacing any existing file
+/// history: The colony's convergence history, see Colony::history
+pub fn write_convergence(path: &Path, history: &[IterationResult]) -> Result<(), Box<dyn Error>> {
+    let (first, last) = match (history.first(), history.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err("the convergence history is empty".into()),
+    };
+    let lowest = history.iter().map(|point| point.global_best).fold(f64::INFINITY, f64::min);
+    let highest = history.iter().map(|point| point.global_best).fold(f64::NEG_INFINITY, f64::max);
+    // A flat curve or a single point still needs ranges with some width
+    let x_range = first.evaluations as f64..(last.evaluations as f64).max(first.evaluations as f64 + 1.0);
+    let y_range = lowest..highest.max(lowest + 1.0);
+
+    let root = SVGBackend::new(path, SPARKLINE_SIZE).into_drawing_area();
+    root.fill(&WHITE)?;
+    let mut chart = ChartBuilder::on(&root).margin(4).build_cartesian_2d(x_range, y_range)?;
+    chart.draw_series(LineSeries::new(
+        history.iter().map(|point| (point.evaluations as f64, point.global_best)),
+        &BLUE,
+    ))?;
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests a short history is drawn to a well-formed SVG and an empty one is refused
+    #[test]
+    fn convergence_sparkline() {
+        let path = std::env::temp_dir().join(format!("aco_convergence_{}.svg", std::process::id()));
+        let history: Vec<IterationResult> = [10.0, 14.0, 14.0, 17.0]
+            .iter()
+            .enumerate()
+            .map(|(i, best)| IterationResult {
+                iteration: i + 1,
+                evaluations: 20 * (i as i64 + 1),
+                iteration_best: *best,
+                global_best: *best,
+                average: best / 2.0,
+                diversity: Some(0.5),
+                entropy: Some(1.0),
+            })
+            .collect();
+        write_convergence(&path, &history).unwrap();
+        let svg = std::fs::read_to_string(&path).unwrap();
+        assert!(svg.trim_start().starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("<polyline"));
+
+        assert!(write_convergence(&path, &[]).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use crate::Parameter;
 
@@ -64,6 +65,48 @@ impl ResearchSet {
         experiment
     }
 
+    /// Runs every parameter setting repeatedly within a total wall-clock
+    /// budget. The budget is split evenly across the settings, and each
+    /// setting is run until its share is used up.
+    /// settings: The parameter settings of the sweep
+    /// total: The wall-clock budget for the whole sweep
+    /// run_once: Runs one setting given the time left for that setting,
+    ///     which should be passed on as the run's time limit
+    /// Returns the results of every completed run, per setting
+    pub fn run_with_budget<R, F>(settings: &[HashMap<String, Parameter>], total: Duration, run_once: F) -> Vec<Vec<R>>
+    where
+        F: FnMut(&HashMap<String, Parameter>, Duration) -> R,
+    {
+        ResearchSet::run_with_clock(settings, total, Instant::now, run_once)
+    }
+
+    /// Runs every parameter setting within a total budget as
+    /// run_with_budget does, reading the time from the given clock
+    /// now: Returns the current time, e.g. Instant::now
+    fn run_with_clock<R, C, F>(settings: &[HashMap<String, Parameter>], total: Duration, mut now: C, mut run_once: F) -> Vec<Vec<R>>
+    where
+        C: FnMut() -> Instant,
+        F: FnMut(&HashMap<String, Parameter>, Duration) -> R,
+    {
+        let share = total / settings.len().max(1) as u32;
+        settings
+            .iter()
+            .map(|parameters| {
+                let start = now();
+                let mut results: Vec<R> = Vec::new();
+                loop {
+                    // A run may overrun the share, which leaves no time rather than underflowing
+                    let time_left = share.saturating_sub(now().duration_since(start));
+                    if time_left.is_zero() {
+                        break;
+                    }
+                    results.push(run_once(parameters, time_left));
+                }
+                results
+            })
+            .collect()
+    }
+
     /// Sets the default parameters to be used in conjunction with
     /// the dependent parameter being tested
     pub fn set_default_parameters() -> HashMap<String, Parameter> {
@@ -78,4 +121,26 @@ impl ResearchSet {
         parameters
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Tests the budget runner splits the budget, passes on the time left and survives an overrun
+    #[test]
+    fn run_with_budget() {
+        let settings = ResearchSet::set_ant_number_params(vec![10, 20]);
+        // Every run takes 20ms of the 50ms share on a fake clock, the
+        // third overruns the share by 10ms
+        let clock = std::cell::Cell::new(Instant::now());
+        let time_lefts = std::cell::RefCell::new(Vec::new());
+        let results = ResearchSet::run_with_clock(&settings, Duration::from_millis(100), || clock.get(), |parameters, time_left| {
+            time_lefts.borrow_mut().push(time_left.as_millis());
+            clock.set(clock.get() + Duration::from_millis(20));
+            Parameter::extract_parameters(parameters).4
+        });
+        assert_eq!(results, vec![vec![10, 10, 10], vec![20, 20, 20]]);
+        assert_eq!(time_lefts.into_inner(), vec![50, 30, 10, 50, 30, 10]);
+    }
+}
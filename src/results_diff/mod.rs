@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+use crate::Column;
+
+/// Columns a row's parameter set is read from
+const PARAMETER_COLUMNS: [Column; 6] = [
+    Column::Alpha,
+    Column::Beta,
+    Column::EvaporationRate,
+    Column::PRate,
+    Column::NumberOfAnts,
+    Column::FitnessEvals,
+];
+
+/// Columns compared between the csvs, higher is better for each
+const METRIC_COLUMNS: [Column; 2] = [Column::TopFitness, Column::FinalAvg];
+
+/// Difference of one metric between the baseline and the candidate,
+/// each the mean over the runs of the parameter set
+///     metric: Header of the metric's column
+///     baseline, candidate: Mean of the metric in each csv
+///     difference: candidate - baseline
+///     regression: True if the candidate is worse than the baseline by
+///         more than the threshold, as a fraction of the baseline
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricDiff {
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub candidate: f64,
+    pub difference: f64,
+    pub regression: bool,
+}
+
+/// Differences of a parameter set found in both csvs
+///     parameters: The parameter set as written in the csv, in the
+///         order Alpha, Beta, Evaporation_Rate, p_rate, Number_Of_Ants,
+///         Fitness_Evals
+///     metrics: Difference of each compared metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowDiff {
+    pub parameters: Vec<String>,
+    pub metrics: Vec<MetricDiff>,
+}
+
+impl RowDiff {
+    /// Returns true if any metric of the parameter set regressed
+    pub fn has_regression(&self) -> bool {
+        self.metrics.iter().any(|metric| metric.regression)
+    }
+}
+
+impl fmt::Display for RowDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.parameters.join(", "))?;
+        for metric in &self.metrics {
+            write!(
+                f,
+                " | {}: {} -> {} ({:+}){}",
+                metric.metric,
+                metric.baseline,
+                metric.candidate,
+                metric.difference,
+                if metric.regression { " REGRESSION" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two results csvs, such as a baseline run against a run of
+/// a modified algorithm. Rows are matched by their parameter set and
+/// repeated runs of a set are averaged before comparing. Parameter
+/// sets only in one of the csvs are left out
+/// baseline: Path of the baseline results csv
+/// candidate: Path of the results csv compared to the baseline
+/// threshold: Fraction of the baseline a metric has to drop by to
+///     count as a regression
+/// Returns the differences in the baseline's row order, or an error if
+/// a csv cannot be read or lacks a parameter or metric column
+pub fn diff_results(baseline: &Path, candidate: &Path, threshold: f64) -> Result<Vec<RowDiff>, Box<dyn Error>> {
+    let baseline = read_means(baseline)?;
+    let candidate: HashMap<Vec<String>, Vec<f64>> = read_means(candidate)?.into_iter().collect();
+    Ok(baseline
+        .into_iter()
+        .filter_map(|(parameters, baseline)| {
+            let candidate = candidate.get(&parameters)?;
+            let metrics = METRIC_COLUMNS
+                .iter()
+                .zip(baseline.iter().zip(candidate))
+                .map(|(column, (baseline, candidate))| MetricDiff {
+                    metric: column.header(),
+                    baseline: *baseline,
+                    candidate: *candidate,
+                    difference: candidate - baseline,
+                    regression: baseline - candidate > threshold * baseline.abs(),
+                })
+                .collect();
+            Some(RowDiff { parameters, metrics })
+        })
+        .collect())
+}
+
+/// A parameter set and the mean of each metric over its runs
+type MeanRow = (Vec<String>, Vec<f64>);
+
+/// Reads a results csv into the mean of each metric per parameter set,
+/// in the order the parameter sets first appear. The metadata comment
+/// line is skipped
+fn read_means(path: &Path) -> Result<Vec<MeanRow>, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new().comment(Some(b'#')).from_path(path)?;
+    let headers = rdr.headers()?.clone();
+    let position = |column: &Column| {
+        headers
+            .iter()
+            .position(|header| header == column.header())
+            .ok_or_else(|| format!("{} has no {} column", path.display(), column.header()))
+    };
+    let parameter_positions = PARAMETER_COLUMNS.iter().map(position).collect::<Result<Vec<usize>, String>>()?;
+    let metric_positions = METRIC_COLUMNS.iter().map(position).collect::<Result<Vec<usize>, String>>()?;
+
+    // Sums of each metric and the number of runs, per parameter set
+    let mut sums: Vec<(Vec<String>, Vec<f64>, f64)> = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let parameters: Vec<String> = parameter_positions.iter().map(|i| record[*i].to_string()).collect();
+        let metrics = metric_positions
+            .iter()
+            .map(|i| record[*i].parse::<f64>())
+            .collect::<Result<Vec<f64>, _>>()?;
+        match sums.iter_mut().find(|(set, _, _)| *set == parameters) {
+            Some((_, totals, runs)) => {
+                totals.iter_mut().zip(&metrics).for_each(|(total, metric)| *total += metric);
+                *runs += 1.0;
+            }
+            None => sums.push((parameters, metrics, 1.0)),
+        }
+    }
+    Ok(sums
+        .into_iter()
+        .map(|(parameters, totals, runs)| (parameters, totals.iter().map(|total| total / runs).collect()))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    /// Tests matching parameter sets are averaged, compared and flagged past the threshold
+    #[test]
+    fn diff_results() {
+        let dir = std::env::temp_dir().join(format!("aco_results_diff_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let header = "Alpha,Beta,Evaporation_Rate,p_rate,Number_Of_Ants,Fitness_Evals,Top_Fitness,Final_avg\n";
+        fs::write(dir.join("baseline.csv"), format!(
+            "# version=0.1.0 seed=none\n{}1,2,0.1,1,20,100,4000,3000\n1,2,0.1,1,20,100,4200,3200\n1,2,0.5,1,20,100,4000,3000\n1,3,0.1,1,20,100,1,1\n",
+            header
+        )).unwrap();
+        fs::write(dir.join("candidate.csv"), format!(
+            "{}1,2,0.5,1,20,100,3500,3000\n1,2,0.1,1,20,100,4150,3050\n",
+            header
+        )).unwrap();
+
+        let diffs = super::diff_results(&dir.join("baseline.csv"), &dir.join("candidate.csv"), 0.05).unwrap();
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].parameters, vec!["1", "2", "0.1", "1", "20", "100"]);
+        assert_eq!(diffs[0].metrics[0], MetricDiff {
+            metric: "Top_Fitness", baseline: 4100.0, candidate: 4150.0, difference: 50.0, regression: false,
+        });
+        assert_eq!(diffs[0].metrics[1].difference, -50.0);
+        assert!(!diffs[0].has_regression());
+        assert_eq!(diffs[1].parameters[2], "0.5");
+        assert!(diffs[1].metrics[0].regression);
+        assert!(!diffs[1].metrics[1].regression);
+
+        fs::write(dir.join("no_metrics.csv"), "Alpha,Beta,Evaporation_Rate,p_rate,Number_Of_Ants,Fitness_Evals\n").unwrap();
+        assert!(super::diff_results(&dir.join("baseline.csv"), &dir.join("no_metrics.csv"), 0.05).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}